@@ -0,0 +1,349 @@
+use crate::config::{Config, Position, Sections, output_matches};
+use crate::modules::tray::TrayBackend;
+use crate::modules::{Context, Event, Module, Registry};
+use crate::services::Services;
+use gtk4::prelude::*;
+use gtk4::{Application, ApplicationWindow, Box, Orientation};
+use gtk4_layer_shell::{Edge, LayerShell};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+/// A running module and the `id` it was given in the config, if any.
+struct Instance {
+    id: Option<String>,
+    module: std::boxed::Box<dyn Module>,
+}
+
+/// One layer-shell window on one output, together with its modules.
+pub struct Bar {
+    window: ApplicationWindow,
+    modules: Rc<RefCell<Vec<Instance>>>,
+}
+
+impl Bar {
+    pub fn new(
+        app: &Application,
+        monitor: &gdk4::Monitor,
+        config: &Config,
+        sections: Sections<'_>,
+        registry: &Registry,
+        shared: &Shared,
+    ) -> Self {
+        let window = ApplicationWindow::builder()
+            .application(app)
+            .title("vibebar-p4")
+            .build();
+
+        let bar = &config.bar;
+        let position = bar.position;
+        let thickness = bar.height + bar.popup_space;
+
+        // Layer Shell configuration
+        window.init_layer_shell();
+        window.set_layer(bar.layer.into());
+        window.set_namespace("vibebar-p4");
+        window.set_monitor(monitor);
+
+        // Anchor to the configured edge and stretch along it
+        window.set_anchor(position.edge(), true);
+        for edge in position.stretch_edges() {
+            window.set_anchor(edge, true);
+        }
+        window.set_margin(Edge::Top, bar.margin.top);
+        window.set_margin(Edge::Bottom, bar.margin.bottom);
+        window.set_margin(Edge::Left, bar.margin.left);
+        window.set_margin(Edge::Right, bar.margin.right);
+
+        // The window is larger than the bar to allow popovers to grow away from the edge
+        if position.is_horizontal() {
+            window.set_default_size(-1, thickness);
+        } else {
+            window.set_default_size(thickness, -1);
+        }
+
+        // Reserve space for the bar (User preference: "keep my css/ultrawide support")
+        window.set_exclusive_zone(if bar.exclusive { bar.height } else { 0 });
+
+        let content = gtk4::CenterBox::new();
+        content.set_widget_name("main-container");
+        // Place the bar against its edge, matching the exclusive zone
+        match position {
+            Position::Top => content.set_valign(gtk4::Align::Start),
+            Position::Bottom => content.set_valign(gtk4::Align::End),
+            Position::Left => content.set_halign(gtk4::Align::Start),
+            Position::Right => content.set_halign(gtk4::Align::End),
+        }
+        if position.is_horizontal() {
+            content.set_height_request(bar.height);
+        } else {
+            content.set_width_request(bar.height);
+        }
+
+        // Sections run along the bar: side-by-side on top/bottom, stacked on left/right
+        let orientation = position.orientation();
+        content.set_orientation(orientation);
+        if orientation == Orientation::Vertical {
+            content.add_css_class("vertical");
+        }
+
+        let left = Box::new(orientation, 0);
+        let center = Box::new(orientation, 0);
+        let right = Box::new(orientation, 0);
+
+        // SizeGroup ensures left and right take equal length (Ultrawide support)
+        let size_group = gtk4::SizeGroup::new(if orientation == Orientation::Horizontal {
+            gtk4::SizeGroupMode::Horizontal
+        } else {
+            gtk4::SizeGroupMode::Vertical
+        });
+        size_group.add_widget(&left);
+        size_group.add_widget(&right);
+
+        if orientation == Orientation::Horizontal {
+            left.set_halign(gtk4::Align::Start);
+            center.set_halign(gtk4::Align::Center);
+            right.set_halign(gtk4::Align::End);
+        } else {
+            left.set_valign(gtk4::Align::Start);
+            center.set_valign(gtk4::Align::Center);
+            right.set_valign(gtk4::Align::End);
+        }
+
+        content.set_start_widget(Some(&left));
+        content.set_center_widget(Some(&center));
+        content.set_end_widget(Some(&right));
+
+        window.set_child(Some(&content));
+
+        // Set input region to only the bar itself to allow clicks to pass through the popup space
+        let height = bar.height;
+        window.connect_realize(move |w| {
+            if let Some(surface) = w.surface() {
+                let rect = match position {
+                    Position::Top => cairo::RectangleInt::new(0, 0, 10000, height),
+                    Position::Bottom => {
+                        cairo::RectangleInt::new(0, thickness - height, 10000, height)
+                    }
+                    Position::Left => cairo::RectangleInt::new(0, 0, height, 10000),
+                    Position::Right => {
+                        cairo::RectangleInt::new(thickness - height, 0, height, 10000)
+                    }
+                };
+                let region = cairo::Region::create_rectangle(&rect);
+                surface.set_input_region(&region);
+            }
+        });
+
+        let ctx = Context {
+            monitor: monitor.connector().map(|s| s.to_string()),
+            tray: shared.tray.clone(),
+            runtime: shared.runtime.clone(),
+            services: shared.services.clone(),
+            popover_position: position.popover_position(),
+            orientation,
+        };
+        let mut instances = Vec::new();
+        for (section, entries) in [
+            (&left, sections.left),
+            (&center, sections.center),
+            (&right, sections.right),
+        ] {
+            for entry in entries {
+                match registry.build(entry, &ctx) {
+                    Ok(mut module) => {
+                        section.append(&module.widget());
+                        module.start();
+                        instances.push(Instance {
+                            id: entry.id.clone(),
+                            module,
+                        });
+                    }
+                    Err(e) => eprintln!("vibebar-p4: failed to build {} module: {}", entry.kind, e),
+                }
+            }
+        }
+
+        // The window owns its modules; stop their update loops once it is gone.
+        let modules = Rc::new(RefCell::new(instances));
+        let modules_destroy = modules.clone();
+        window.connect_destroy(move |_| {
+            for mut instance in modules_destroy.borrow_mut().drain(..) {
+                instance.module.stop();
+            }
+        });
+
+        window.present();
+
+        Self { window, modules }
+    }
+
+    pub fn handle_event(&self, event: &Event) {
+        for instance in self.modules.borrow_mut().iter_mut() {
+            instance.module.handle_event(event);
+        }
+    }
+
+    /// Delivers `event` to the modules with the given `id`; returns how many there were.
+    pub fn send(&self, id: &str, event: &Event) -> usize {
+        let mut matched = 0;
+        for instance in self.modules.borrow_mut().iter_mut() {
+            if instance.id.as_deref() == Some(id) {
+                instance.module.handle_event(event);
+                matched += 1;
+            }
+        }
+        matched
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.window.is_visible()
+    }
+
+    /// Maps or unmaps the window; a hidden bar gives up its exclusive zone.
+    pub fn set_visible(&self, visible: bool) {
+        self.window.set_visible(visible);
+    }
+
+    /// Closes the window; its modules are stopped from the destroy handler.
+    pub fn destroy(self) {
+        self.window.destroy();
+    }
+}
+
+/// Backends that outlive any single bar, handed to every module through its [`Context`].
+#[derive(Clone)]
+pub struct Shared {
+    pub runtime: Handle,
+    pub tray: Option<Arc<TrayBackend>>,
+    pub services: Rc<Services>,
+}
+
+/// Keeps one [`Bar`] per connected output that the config allows.
+///
+/// [`Shared`] backends live here rather than in the bars, so they survive
+/// outputs coming and going.
+pub struct Bars {
+    app: Application,
+    config: Rc<Config>,
+    registry: Rc<Registry>,
+    shared: Shared,
+    /// Every known output, with its bar unless the config excludes it.
+    bars: Vec<(gdk4::Monitor, Option<Bar>)>,
+    // The application would quit while no output has a bar otherwise.
+    _hold: gtk4::gio::ApplicationHoldGuard,
+}
+
+impl Bars {
+    pub fn new(
+        app: &Application,
+        config: Rc<Config>,
+        registry: Rc<Registry>,
+        shared: Shared,
+    ) -> Self {
+        Self {
+            app: app.clone(),
+            config,
+            registry,
+            shared,
+            bars: Vec::new(),
+            _hold: app.hold(),
+        }
+    }
+
+    /// Creates bars for new outputs and destroys those whose output is gone.
+    pub fn sync(&mut self, monitors: &gtk4::gio::ListModel) {
+        let current: Vec<gdk4::Monitor> = (0..monitors.n_items())
+            .filter_map(|i| monitors.item(i).and_downcast::<gdk4::Monitor>())
+            .collect();
+
+        let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.bars)
+            .into_iter()
+            .partition(|(monitor, _)| current.contains(monitor));
+        for bar in removed.into_iter().filter_map(|(_, bar)| bar) {
+            bar.destroy();
+        }
+        self.bars = kept;
+
+        for monitor in current {
+            if !self.bars.iter().any(|(m, _)| *m == monitor) {
+                let bar = self.create(&monitor);
+                self.bars.push((monitor, bar));
+            }
+        }
+    }
+
+    /// Rebuilds the bar of one output, e.g. after its connector name became known.
+    pub fn rebuild(&mut self, monitor: &gdk4::Monitor) {
+        if let Some(index) = self.bars.iter().position(|(m, _)| m == monitor) {
+            if let Some(bar) = self.bars[index].1.take() {
+                bar.destroy();
+            }
+            self.bars[index].1 = self.create(monitor);
+        }
+    }
+
+    /// Replaces the config and recreates every bar with it.
+    pub fn reload(&mut self, config: Rc<Config>, monitors: &gtk4::gio::ListModel) {
+        self.config = config;
+        for bar in self.bars.drain(..).filter_map(|(_, bar)| bar) {
+            bar.destroy();
+        }
+        self.sync(monitors);
+    }
+
+    pub fn broadcast(&self, event: &Event) {
+        for bar in self.bars.iter().filter_map(|(_, bar)| bar.as_ref()) {
+            bar.handle_event(event);
+        }
+    }
+
+    /// Sends `event` to the modules with the given `id` on every bar.
+    pub fn send(&self, id: &str, event: &Event) -> usize {
+        self.bars
+            .iter()
+            .filter_map(|(_, bar)| bar.as_ref())
+            .map(|bar| bar.send(id, event))
+            .sum()
+    }
+
+    /// Sets the visibility of the bars on outputs matching `output`, or of every
+    /// bar, to `visible(current)`; returns how many bars matched.
+    pub fn set_visible(&self, output: Option<&str>, visible: impl Fn(bool) -> bool) -> usize {
+        let mut matched = 0;
+        for (monitor, bar) in &self.bars {
+            let Some(bar) = bar else {
+                continue;
+            };
+            if let Some(pattern) = output
+                && !output_matches(
+                    pattern,
+                    monitor.connector().as_deref(),
+                    monitor.description().as_deref(),
+                )
+            {
+                continue;
+            }
+            bar.set_visible(visible(bar.is_visible()));
+            matched += 1;
+        }
+        matched
+    }
+
+    fn create(&self, monitor: &gdk4::Monitor) -> Option<Bar> {
+        let connector = monitor.connector();
+        let description = monitor.description();
+        let sections = self
+            .config
+            .sections_for(connector.as_deref(), description.as_deref())?;
+        Some(Bar::new(
+            &self.app,
+            monitor,
+            &self.config,
+            sections,
+            &self.registry,
+            &self.shared,
+        ))
+    }
+}
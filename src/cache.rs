@@ -0,0 +1,88 @@
+//! Last results of expensive checks, such as polled scripts and update
+//! checks, kept across restarts so a new bar has something to show right
+//! away and the checks are not re-run early.
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    /// Unix time of the run, in seconds.
+    timestamp: u64,
+    value: T,
+}
+
+/// The cache file of one check.
+pub struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    /// Entries are keyed by everything that shapes the result, e.g. a
+    /// script's command, so editing it starts afresh.
+    pub fn new(kind: &str, key: &str) -> Self {
+        Self {
+            path: cache_dir().join(format!("{}-{:016x}.json", kind, fnv1a(key))),
+        }
+    }
+
+    /// The cached value and how long ago it was produced.
+    pub async fn load<T: DeserializeOwned>(&self) -> Option<(T, Duration)> {
+        let text = tokio::fs::read_to_string(&self.path).await.ok()?;
+        let entry: Entry<T> = serde_json::from_str(&text).ok()?;
+        let age = now().saturating_sub(entry.timestamp);
+        Some((entry.value, Duration::from_secs(age)))
+    }
+
+    pub async fn store<T: Serialize>(&self, value: &T) {
+        let entry = Entry {
+            timestamp: now(),
+            value,
+        };
+        if let Err(e) = self.write(&entry).await {
+            eprintln!("vibebar-p4: {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Writes through a temporary file so readers never see half an entry.
+    /// Its name is unique to this write, as other bar processes may be
+    /// storing the same command's output at the same time.
+    async fn write<T: Serialize>(&self, entry: &Entry<T>) -> std::io::Result<()> {
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let tmp = self.path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        tokio::fs::write(&tmp, serde_json::to_vec(entry)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await
+    }
+}
+
+/// `$XDG_CACHE_HOME/vibebar-p4`, or `~/.cache/vibebar-p4` when unset.
+fn cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+        .unwrap_or_default()
+        .join("vibebar-p4")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across builds.
+pub fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
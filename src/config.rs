@@ -0,0 +1,313 @@
+use crate::modules::Registry;
+use gtk4::{Orientation, PositionType};
+use gtk4_layer_shell::{Edge, Layer};
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Layout used when the user has no config file, matching the original hard-coded bar.
+const DEFAULT_CONFIG: &str = include_str!("config.toml");
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub bar: BarConfig,
+    #[serde(default)]
+    pub left: Vec<ModuleConfig>,
+    #[serde(default)]
+    pub center: Vec<ModuleConfig>,
+    #[serde(default)]
+    pub right: Vec<ModuleConfig>,
+    #[serde(default)]
+    pub outputs: OutputsConfig,
+}
+
+/// Which outputs get a bar, and per-output replacements for the default layout.
+///
+/// Outputs are matched by connector name (`DP-1`) or by a substring of their
+/// description (`Dell Inc. DELL U2720Q`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputsConfig {
+    /// Outputs that get a bar; every output when empty.
+    pub allow: Vec<String>,
+    /// Outputs that never get a bar, even when allowed.
+    pub deny: Vec<String>,
+    /// Layout overrides; the first matching entry wins.
+    pub layout: Vec<OutputLayout>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputLayout {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    /// Sections left out here keep the default layout.
+    pub left: Option<Vec<ModuleConfig>>,
+    pub center: Option<Vec<ModuleConfig>>,
+    pub right: Option<Vec<ModuleConfig>>,
+}
+
+/// The modules of one bar, after per-output overrides are applied.
+pub struct Sections<'a> {
+    pub left: &'a [ModuleConfig],
+    pub center: &'a [ModuleConfig],
+    pub right: &'a [ModuleConfig],
+}
+
+/// Placement of the bar window on its output.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BarConfig {
+    pub position: Position,
+    /// Thickness of the bar in pixels.
+    pub height: i32,
+    pub layer: BarLayer,
+    /// Reserve space for the bar so other windows are not placed under it.
+    pub exclusive: bool,
+    pub margin: Margins,
+    /// Transparent, click-through room next to the bar that popovers can grow into.
+    pub popup_space: i32,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            position: Position::Bottom,
+            height: 24,
+            layer: BarLayer::Top,
+            exclusive: true,
+            margin: Margins::default(),
+            popup_space: 776,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Margins {
+    pub top: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub right: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Position {
+    /// The screen edge the bar is attached to.
+    pub fn edge(self) -> Edge {
+        match self {
+            Position::Top => Edge::Top,
+            Position::Bottom => Edge::Bottom,
+            Position::Left => Edge::Left,
+            Position::Right => Edge::Right,
+        }
+    }
+
+    /// The two edges perpendicular to [`Position::edge`] that the bar stretches between.
+    pub fn stretch_edges(self) -> [Edge; 2] {
+        if self.is_horizontal() {
+            [Edge::Left, Edge::Right]
+        } else {
+            [Edge::Top, Edge::Bottom]
+        }
+    }
+
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Position::Top | Position::Bottom)
+    }
+
+    /// Direction sections and modules are laid out in.
+    pub fn orientation(self) -> Orientation {
+        if self.is_horizontal() {
+            Orientation::Horizontal
+        } else {
+            Orientation::Vertical
+        }
+    }
+
+    /// Direction popovers open in: away from the attached edge.
+    pub fn popover_position(self) -> PositionType {
+        match self {
+            Position::Top => PositionType::Bottom,
+            Position::Bottom => PositionType::Top,
+            Position::Left => PositionType::Right,
+            Position::Right => PositionType::Left,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarLayer {
+    Background,
+    Bottom,
+    Top,
+    Overlay,
+}
+
+impl From<BarLayer> for Layer {
+    fn from(layer: BarLayer) -> Self {
+        match layer {
+            BarLayer::Background => Layer::Background,
+            BarLayer::Bottom => Layer::Bottom,
+            BarLayer::Top => Layer::Top,
+            BarLayer::Overlay => Layer::Overlay,
+        }
+    }
+}
+
+/// One module instance in a bar section, selected by its `type` key.
+///
+/// The remaining keys are module specific and checked by the module itself.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ModuleConfig {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Name the module is addressed by over the control socket.
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub options: toml::Table,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConfigError::Invalid(path, msg) => write!(f, "{}: {}", path.display(), msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the user config, falling back to the built-in layout when none exists.
+    pub fn load(registry: &Registry) -> Result<Self, ConfigError> {
+        let path = config_path();
+        if path.exists() {
+            Self::from_file(&path, registry)
+        } else {
+            Self::parse(Path::new("<built-in config>"), DEFAULT_CONFIG, registry)
+        }
+    }
+
+    pub fn from_file(path: &Path, registry: &Registry) -> Result<Self, ConfigError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| ConfigError::Read(path.to_path_buf(), e))?;
+        Self::parse(path, &text, registry)
+    }
+
+    fn parse(path: &Path, text: &str, registry: &Registry) -> Result<Self, ConfigError> {
+        let config: Config =
+            toml::from_str(text).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+        config
+            .validate(registry)
+            .map_err(|msg| ConfigError::Invalid(path.to_path_buf(), msg))?;
+        Ok(config)
+    }
+
+    fn validate(&self, registry: &Registry) -> Result<(), String> {
+        if self.bar.height <= 0 {
+            return Err("bar.height must be positive".to_string());
+        }
+        if self.bar.popup_space < 0 {
+            return Err("bar.popup-space must not be negative".to_string());
+        }
+        validate_section(registry, "left", &self.left)?;
+        validate_section(registry, "center", &self.center)?;
+        validate_section(registry, "right", &self.right)?;
+        for (i, layout) in self.outputs.layout.iter().enumerate() {
+            for (name, modules) in [
+                ("left", &layout.left),
+                ("center", &layout.center),
+                ("right", &layout.right),
+            ] {
+                if let Some(modules) = modules {
+                    let section = format!("outputs.layout[{}].{}", i, name);
+                    validate_section(registry, &section, modules)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The layout for an output, or `None` when it should not get a bar.
+    pub fn sections_for(
+        &self,
+        connector: Option<&str>,
+        description: Option<&str>,
+    ) -> Option<Sections<'_>> {
+        let matches = |pattern: &String| output_matches(pattern, connector, description);
+        if !self.outputs.allow.is_empty() && !self.outputs.allow.iter().any(matches) {
+            return None;
+        }
+        if self.outputs.deny.iter().any(matches) {
+            return None;
+        }
+
+        let layout = self
+            .outputs
+            .layout
+            .iter()
+            .find(|layout| matches(&layout.pattern));
+        Some(Sections {
+            left: layout.and_then(|l| l.left.as_deref()).unwrap_or(&self.left),
+            center: layout
+                .and_then(|l| l.center.as_deref())
+                .unwrap_or(&self.center),
+            right: layout
+                .and_then(|l| l.right.as_deref())
+                .unwrap_or(&self.right),
+        })
+    }
+}
+
+fn validate_section(
+    registry: &Registry,
+    section: &str,
+    modules: &[ModuleConfig],
+) -> Result<(), String> {
+    for (i, module) in modules.iter().enumerate() {
+        registry
+            .validate(module)
+            .map_err(|msg| format!("{}[{}] ({}): {}", section, i, module.kind, msg))?;
+    }
+    Ok(())
+}
+
+/// Whether an output is selected by `pattern`: its connector name, or part of its description.
+pub fn output_matches(pattern: &str, connector: Option<&str>, description: Option<&str>) -> bool {
+    connector == Some(pattern) || description.is_some_and(|d| d.contains(pattern))
+}
+
+/// `$XDG_CONFIG_HOME/vibebar-p4`, or `~/.config/vibebar-p4` when unset.
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .unwrap_or_default()
+        .join("vibebar-p4")
+}
+
+fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
@@ -0,0 +1,81 @@
+//! Hyprland's IPC: `.socket.sock` answers one request per connection, while
+//! `.socket2.sock` streams `event>>data` lines.
+use serde::de::DeserializeOwned;
+use std::io;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+
+const SIGNATURE_ENV: &str = "HYPRLAND_INSTANCE_SIGNATURE";
+
+/// Whether the bar runs under Hyprland, which sets `$HYPRLAND_INSTANCE_SIGNATURE`.
+pub fn detected() -> bool {
+    std::env::var_os(SIGNATURE_ENV).is_some_and(|v| !v.is_empty())
+}
+
+/// `$XDG_RUNTIME_DIR/hypr/<signature>`, or `/tmp/hypr/<signature>` as used by
+/// Hyprland before 0.40.
+fn socket_dir() -> io::Result<PathBuf> {
+    let signature = std::env::var_os(SIGNATURE_ENV).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not set", SIGNATURE_ENV),
+        )
+    })?;
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    let dir = runtime_dir
+        .map(|dir| dir.join("hypr").join(&signature))
+        .filter(|dir| dir.exists())
+        .unwrap_or_else(|| PathBuf::from("/tmp/hypr").join(&signature));
+    Ok(dir)
+}
+
+/// Sends `request`, e.g. `j/workspaces`, and returns the whole reply.
+pub async fn request(request: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket_dir()?.join(".socket.sock")).await?;
+    stream.write_all(request.as_bytes()).await?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).await?;
+    Ok(reply)
+}
+
+/// Sends a `j/` request and parses the JSON reply.
+pub async fn query<T: DeserializeOwned>(what: &str) -> io::Result<T> {
+    let reply = request(&format!("j/{}", what)).await?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+/// Runs a dispatcher, e.g. `dispatch("workspace", "3")`.
+pub async fn dispatch(dispatcher: &str, args: &str) -> io::Result<()> {
+    let reply = request(&format!("dispatch {} {}", dispatcher, args)).await?;
+    if reply.trim() == "ok" {
+        Ok(())
+    } else {
+        Err(io::Error::other(reply))
+    }
+}
+
+pub struct Events {
+    lines: Lines<BufReader<UnixStream>>,
+}
+
+impl Events {
+    pub async fn connect() -> io::Result<Self> {
+        let stream = UnixStream::connect(socket_dir()?.join(".socket2.sock")).await?;
+        Ok(Self {
+            lines: BufReader::new(stream).lines(),
+        })
+    }
+
+    /// The next event's name and data, e.g. `("workspace", "3")`.
+    /// Cancelling it loses no event, as `Lines` keeps any partial line.
+    pub async fn next(&mut self) -> io::Result<(String, String)> {
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        let (name, data) = line.split_once(">>").unwrap_or((&line, ""));
+        Ok((name.to_string(), data.to_string()))
+    }
+}
@@ -0,0 +1,224 @@
+//! Control socket of a running bar, and the `vibebar-p4 msg` client for it.
+//!
+//! The protocol is one JSON object per line in each direction, e.g.
+//! `{"command":"toggle","output":"DP-1"}` answered by `{"ok":true}`.
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, oneshot};
+
+const USAGE: &str = "usage: vibebar-p4 msg <command>
+
+commands:
+  show [OUTPUT]            show the bar on OUTPUT, or on every output
+  hide [OUTPUT]            hide the bar on OUTPUT, or on every output
+  toggle [OUTPUT]          toggle the bar on OUTPUT, or on every output
+  reload                   re-read config.toml and style.css
+  refresh [ID]             update the module with this id, or every module
+  set-text ID [TEXT...]    replace the text of a custom module";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Request {
+    /// Outputs are matched like in `[outputs]`; `None` means every output.
+    Show {
+        output: Option<String>,
+    },
+    Hide {
+        output: Option<String>,
+    },
+    Toggle {
+        output: Option<String>,
+    },
+    Reload,
+    Refresh {
+        module: Option<String>,
+    },
+    SetText {
+        module: String,
+        text: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            error: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+        }
+    }
+}
+
+/// A request waiting to be answered on the GTK thread.
+pub type Call = (Request, oneshot::Sender<Response>);
+
+/// `$XDG_RUNTIME_DIR/vibebar-p4.sock`.
+pub fn socket_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|v| !v.is_empty())
+        .map(|dir| PathBuf::from(dir).join("vibebar-p4.sock"))
+}
+
+/// The listening socket; removed from disk when dropped.
+pub struct Server {
+    path: PathBuf,
+    calls: Option<mpsc::UnboundedReceiver<Call>>,
+}
+
+impl Server {
+    /// Binds the control socket and accepts clients on `runtime`.
+    pub fn bind(runtime: &Handle) -> io::Result<Self> {
+        let path = socket_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+
+        // A socket nobody answers on is left over from an instance that crashed.
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("another instance is listening on {}", path.display()),
+            ));
+        }
+        let _ = std::fs::remove_file(&path);
+
+        let listener = {
+            let _guard = runtime.enter();
+            UnixListener::bind(&path)?
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        runtime.spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_client(stream, tx.clone()));
+            }
+        });
+
+        Ok(Self {
+            path,
+            calls: Some(rx),
+        })
+    }
+
+    /// Incoming requests; can be taken once.
+    pub fn calls(&mut self) -> Option<mpsc::UnboundedReceiver<Call>> {
+        self.calls.take()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn serve_client(stream: UnixStream, calls: mpsc::UnboundedSender<Call>) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                if calls.send((request, reply_tx)).is_err() {
+                    break;
+                }
+                reply_rx
+                    .await
+                    .unwrap_or_else(|_| Response::error("bar is shutting down"))
+            }
+            Err(e) => Response::error(format!("invalid request: {}", e)),
+        };
+        let mut reply = serde_json::to_string(&response).unwrap_or_default();
+        reply.push('\n');
+        if write.write_all(reply.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Entry point of `vibebar-p4 msg`; returns the process exit code.
+pub fn run_msg(args: &[String]) -> i32 {
+    let request = match parse_args(args) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("vibebar-p4: {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+    match send(&request) {
+        Ok(Response { ok: true, .. }) => 0,
+        Ok(Response { error, .. }) => {
+            eprintln!("vibebar-p4: {}", error.unwrap_or_default());
+            1
+        }
+        Err(e) => {
+            eprintln!("vibebar-p4: could not reach the running bar: {}", e);
+            1
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Request, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Err("missing command".to_string());
+    };
+    let optional = |what: &str| match rest {
+        [] => Ok(None),
+        [value] => Ok(Some(value.clone())),
+        _ => Err(format!("`{}` takes at most one {}", command, what)),
+    };
+    match command.as_str() {
+        "show" => Ok(Request::Show {
+            output: optional("output")?,
+        }),
+        "hide" => Ok(Request::Hide {
+            output: optional("output")?,
+        }),
+        "toggle" => Ok(Request::Toggle {
+            output: optional("output")?,
+        }),
+        "reload" if rest.is_empty() => Ok(Request::Reload),
+        "reload" => Err("`reload` takes no arguments".to_string()),
+        "refresh" => Ok(Request::Refresh {
+            module: optional("module id")?,
+        }),
+        "set-text" => match rest.split_first() {
+            Some((module, text)) => Ok(Request::SetText {
+                module: module.clone(),
+                text: text.join(" "),
+            }),
+            None => Err("`set-text` needs a module id".to_string()),
+        },
+        other => Err(format!("unknown command `{}`", other)),
+    }
+}
+
+fn send(request: &Request) -> io::Result<Response> {
+    let path = socket_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+    let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    io::BufReader::new(stream).read_line(&mut reply)?;
+    Ok(serde_json::from_str(&reply)?)
+}
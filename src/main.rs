@@ -1,171 +1,228 @@
+use bar::{Bars, Shared};
+use config::{Config, ConfigError};
+use gtk4::Application;
 use gtk4::prelude::*;
-use gtk4::{Application, ApplicationWindow, Box, Orientation};
-use gtk4_layer_shell::{Edge, Layer, LayerShell};
-use std::sync::Arc;
-
+use ipc::{Request, Response};
+use modules::{Event, Registry};
+use services::Services;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+mod bar;
+mod cache;
+mod config;
+mod hyprland;
+mod ipc;
 mod modules;
+mod niri;
+mod services;
+mod style;
 
-fn create_window(
-    app: &Application,
-    monitor: &gdk4::Monitor,
-    tray_backend: Option<Arc<modules::tray::TrayBackend>>,
-) {
-    let window = ApplicationWindow::builder()
-        .application(app)
-        .title("vibebar-p4")
-        .build();
-
-    // Layer Shell configuration
-    window.init_layer_shell();
-    window.set_layer(Layer::Top);
-    window.set_namespace("vibebar-p4");
-    window.set_monitor(monitor);
-
-    // Anchor to bottom, left, and right
-    window.set_anchor(Edge::Bottom, true);
-    window.set_anchor(Edge::Left, true);
-    window.set_anchor(Edge::Right, true);
-
-    // Window is 800px tall to allow popovers to grow upwards
-    window.set_default_size(-1, 800);
-
-    // Reserve 24px of space for the bar (User preference: "keep my css/ultrawide support")
-    window.set_exclusive_zone(24);
-
-    let content = gtk4::CenterBox::new();
-    content.set_widget_name("main-container");
-    content.set_valign(gtk4::Align::End); // Place bar at the bottom
-    content.set_height_request(24); // Match exclusive zone height
-
-    let left = Box::new(Orientation::Horizontal, 0);
-    let center = Box::new(Orientation::Horizontal, 0);
-    let right = Box::new(Orientation::Horizontal, 0);
-
-    // SizeGroup ensures left and right take equal width (Ultrawide support)
-    let size_group = gtk4::SizeGroup::new(gtk4::SizeGroupMode::Horizontal);
-    size_group.add_widget(&left);
-    size_group.add_widget(&right);
-
-    left.set_halign(gtk4::Align::Start);
-    center.set_halign(gtk4::Align::Center);
-    right.set_halign(gtk4::Align::End);
-
-    content.set_start_widget(Some(&left));
-    content.set_center_widget(Some(&center));
-    content.set_end_widget(Some(&right));
-
-    window.set_child(Some(&content));
-
-    // Set input region to only the bottom 24px to allow clicks to pass through above the bar
-    window.connect_realize(|w| {
-        if let Some(surface) = w.surface() {
-            let rect = cairo::RectangleInt::new(0, 800 - 24, 10000, 24);
-            let region = cairo::Region::create_rectangle(&rect);
-            surface.set_input_region(&region);
-        }
-    });
-
-    // Initialize modules - Left (User Layout)
-    modules::disk::init(&left, "/", " ");
-    modules::disk::init(&left, "/mnt/storage", " ");
-    modules::ram::init(&left);
-    modules::gpu::init(&left);
-    modules::cpu::init(&left);
-
-    // Initialize modules - Center
-    // Use friend's monitor-aware signature if possible, or fallback
-    // Friend's signature: init(&Box, Option<String>)
-    modules::workspaces::init(&center, monitor.connector().map(|s| s.to_string()));
-
-    modules::mpris::init(&right);
-    modules::scripts::init(&right, "checkupdates | wc -l", 3600, "", None);
-    modules::scripts::init(
-        &right,
-        "~/.config/waybar/scripts/airqualityindex.sh",
-        1800,
-        "",
-        None,
-    );
-
-    modules::network::init(&right);
-
-    modules::volume::init(&right);
-    modules::clock::init(&right);
-
-    if let Some(backend) = tray_backend {
-        modules::tray::init(&right, backend);
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("msg") {
+        std::process::exit(ipc::run_msg(&args[2..]));
     }
 
-    window.present();
-}
+    let registry = Rc::new(Registry::with_builtins());
+    let config = match Config::load(&registry) {
+        Ok(config) => Rc::new(config),
+        Err(e) => {
+            eprintln!("vibebar-p4: invalid config: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // One runtime for all background work, shut down once the application exits
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("vibebar-p4-worker")
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("vibebar-p4: failed to start the async runtime: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // Lets code on the GTK thread use tokio types that expect a runtime context
+    let _guard = runtime.enter();
+    let handle = runtime.handle().clone();
+
+    let mut server = match ipc::Server::bind(&handle) {
+        Ok(server) => Some(server),
+        Err(e) => {
+            eprintln!("vibebar-p4: control socket disabled: {}", e);
+            None
+        }
+    };
+    let calls = Cell::new(server.as_mut().and_then(ipc::Server::calls));
 
-fn main() {
     let app = Application::builder()
         .application_id("com.github.hal.vibebar-p4")
         .build();
 
-    app.connect_activate(|app| {
-        // Load CSS once
-        let provider = gtk4::CssProvider::new();
-        // Load the user's restored style.css
-        provider.load_from_data(include_str!("style.css"));
-
-        let tray_backend = if let Ok(rt) = tokio::runtime::Runtime::new() {
-            let rt = std::boxed::Box::leak(std::boxed::Box::new(rt));
-            std::mem::forget(rt.enter());
-            rt.block_on(async { modules::tray::TrayBackend::new().await })
-        } else {
-            None
+    app.connect_activate(move |app| {
+        let tray_backend = handle.block_on(modules::tray::TrayBackend::new());
+
+        let Some(display) = gdk4::Display::default() else {
+            return;
         };
+        let style = style::init(&display);
+
+        // Create window for each monitor
+        let bars = Rc::new(RefCell::new(Bars::new(
+            app,
+            config.clone(),
+            registry.clone(),
+            Shared {
+                runtime: handle.clone(),
+                tray: tray_backend,
+                services: Rc::new(Services::new(handle.clone())),
+            },
+        )));
+        let monitors = display.monitors();
+        bars.borrow_mut().sync(&monitors);
+        follow_names(&bars, &monitors, 0, monitors.n_items());
+
+        // Handle monitor changes in-process, keeping shared backends alive
+        let bars_hotplug = bars.clone();
+        monitors.connect_items_changed(move |monitors, position, _removed, added| {
+            bars_hotplug.borrow_mut().sync(monitors);
+            follow_names(&bars_hotplug, monitors, position, added);
+        });
 
-        if let Some(display) = gdk4::Display::default() {
-            gtk4::style_context_add_provider_for_display(
-                &display,
-                &provider,
-                gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-            );
-
-            // Create window for each monitor
-            let monitors = display.monitors();
-            for i in 0..monitors.n_items() {
-                if let Some(monitor) = monitors
-                    .item(i)
-                    .and_then(|m| m.downcast::<gdk4::Monitor>().ok())
-                {
-                    create_window(app, &monitor, tray_backend.clone());
-                }
+        // Handle SIGUSR1 to refresh every module immediately
+        // 10 is SIGUSR1 on Linux
+        let bars_refresh = bars.clone();
+        glib::unix_signal_add_local(10, move || {
+            bars_refresh.borrow().broadcast(&Event::Refresh);
+            glib::ControlFlow::Continue
+        });
+
+        // Handle SIGUSR2 to reload the config and rebuild the bars
+        // 12 is SIGUSR2 on Linux
+        let bars_reload = bars.clone();
+        let registry_reload = registry.clone();
+        let monitors_reload = monitors.clone();
+        glib::unix_signal_add_local(12, move || {
+            if let Err(e) = reload(&bars_reload, &registry_reload, &monitors_reload) {
+                eprintln!("vibebar-p4: keeping previous config: {}", e);
             }
+            glib::ControlFlow::Continue
+        });
 
-            // Handle monitor changes
-            monitors.connect_items_changed(move |_, _, _, _| {
-                let _ = std::process::Command::new("killall")
-                    .arg("-SIGUSR2")
-                    .arg("vibebar-p4")
-                    .spawn();
+        // Answer requests from the control socket
+        if let Some(mut calls) = calls.take() {
+            let registry = registry.clone();
+            glib::MainContext::default().spawn_local(async move {
+                while let Some((request, reply)) = calls.recv().await {
+                    let response = dispatch(request, &bars, &registry, &monitors, &style);
+                    let _ = reply.send(response);
+                }
             });
         }
+    });
 
-        // Handle SIGUSR2 for restart
-        // 12 is SIGUSR2 on Linux
-        glib::unix_signal_add_local(12, move || {
-            let exe = std::env::current_exe().unwrap();
-            let args: Vec<_> = std::env::args_os().collect();
+    app.run();
 
-            // Prepare CStrings for execv
-            use std::ffi::CString;
-            use std::os::unix::ffi::OsStrExt;
+    // Give workers a moment to finish; blocking ones (D-Bus, PulseAudio) are abandoned
+    drop(_guard);
+    runtime.shutdown_timeout(Duration::from_secs(1));
+    drop(server);
+}
+
+/// Outputs can be announced before their name is known; rebuilds the bars of
+/// `count` monitors from `position` once it is, so output rules and workspace
+/// filters bind to the right name.
+fn follow_names(
+    bars: &Rc<RefCell<Bars>>,
+    monitors: &gtk4::gio::ListModel,
+    position: u32,
+    count: u32,
+) {
+    for i in position..position + count {
+        let Some(monitor) = monitors.item(i).and_downcast::<gdk4::Monitor>() else {
+            continue;
+        };
+        // Both usually arrive together; one rebuild covers them
+        let pending = Rc::new(Cell::new(false));
+        let bars = bars.clone();
+        let schedule = Rc::new(move |monitor: &gdk4::Monitor| {
+            if pending.replace(true) {
+                return;
+            }
+            let bars = bars.clone();
+            let pending = pending.clone();
+            let monitor = monitor.clone();
+            glib::idle_add_local_once(move || {
+                pending.set(false);
+                bars.borrow_mut().rebuild(&monitor);
+            });
+        });
+        let schedule_connector = schedule.clone();
+        monitor.connect_connector_notify(move |monitor| schedule_connector(monitor));
+        monitor.connect_description_notify(move |monitor| schedule(monitor));
+    }
+}
 
-            let path_c = CString::new(exe.as_os_str().as_bytes()).unwrap();
-            let args_c: Vec<CString> = args
-                .iter()
-                .map(|arg| CString::new(arg.as_bytes()).unwrap())
-                .collect();
+/// Re-reads the config and rebuilds every bar with it.
+fn reload(
+    bars: &RefCell<Bars>,
+    registry: &Registry,
+    monitors: &gtk4::gio::ListModel,
+) -> Result<(), ConfigError> {
+    let config = Config::load(registry)?;
+    bars.borrow_mut().reload(Rc::new(config), monitors);
+    Ok(())
+}
 
-            let _ = nix::unistd::execv(&path_c, &args_c);
+fn dispatch(
+    request: Request,
+    bars: &RefCell<Bars>,
+    registry: &Registry,
+    monitors: &gtk4::gio::ListModel,
+    style: &style::Style,
+) -> Response {
+    match request {
+        Request::Show { output } => set_visible(bars, output, |_| true),
+        Request::Hide { output } => set_visible(bars, output, |_| false),
+        Request::Toggle { output } => set_visible(bars, output, |visible| !visible),
+        Request::Reload => {
+            style.reload();
+            match reload(bars, registry, monitors) {
+                Ok(()) => Response::ok(),
+                Err(e) => Response::error(format!("keeping previous config: {}", e)),
+            }
+        }
+        Request::Refresh { module: None } => {
+            bars.borrow().broadcast(&Event::Refresh);
+            Response::ok()
+        }
+        Request::Refresh { module: Some(id) } => send(bars, &id, Event::Refresh),
+        Request::SetText { module, text } => send(bars, &module, Event::SetText(text)),
+    }
+}
 
-            glib::ControlFlow::Break
-        });
-    });
+fn set_visible(
+    bars: &RefCell<Bars>,
+    output: Option<String>,
+    visible: impl Fn(bool) -> bool,
+) -> Response {
+    match (
+        bars.borrow().set_visible(output.as_deref(), visible),
+        output,
+    ) {
+        (0, Some(output)) => Response::error(format!("no bar on output `{}`", output)),
+        _ => Response::ok(),
+    }
+}
 
-    app.run();
+fn send(bars: &RefCell<Bars>, id: &str, event: Event) -> Response {
+    if bars.borrow().send(id, &event) == 0 {
+        Response::error(format!("no module with id `{}`", id))
+    } else {
+        Response::ok()
+    }
 }
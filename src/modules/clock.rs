@@ -1,63 +1,126 @@
+use chrono::format::{Item, StrftimeItems};
 use chrono::{Datelike, Local};
 use gtk4::prelude::*;
-use gtk4::{Button, EventControllerMotion, Label, Popover, PositionType};
+use gtk4::{Button, EventControllerMotion, Label, Popover};
+use serde::Deserialize;
 use std::time::Duration;
 
-pub fn init(container: &gtk4::Box) {
-    // 1. Create a Button instead of a Label
-    let button = Button::builder()
-        .label(format!("  {}", Local::now().format("%a %d %b %H:%M")))
-        .has_frame(false) // Makes it look flat, common for status bars
-        .build();
-
-    button.add_css_class("btn");
-
-    container.append(&button);
-
-    // 2. Setup the Popover
-    let popover = Popover::builder()
-        .position(PositionType::Top)
-        .autohide(false)
-        .has_arrow(true)
-        .build();
-
-    // Set the button as the parent of the popover
-    popover.set_parent(&button);
-
-    let popover_label = Label::builder()
-        .use_markup(true)
-        .label(get_calendar_markup())
-        .build();
-    popover_label.set_widget_name("popover-label");
-    popover.set_child(Some(&popover_label));
-
-    // 3. Hover logic using EventControllerMotion on the Button
-    let motion_controller = EventControllerMotion::new();
-
-    let p_enter = popover.clone();
-    motion_controller.connect_enter(move |_, _, _| {
-        p_enter.popup();
-    });
-
-    let p_leave = popover.clone();
-    motion_controller.connect_leave(move |_| {
-        p_leave.popdown();
-    });
-
-    button.add_controller(motion_controller);
-
-    // 4. Update loop
-    let p_label = popover_label.clone();
-    let b_clone = button.clone();
-    glib::timeout_add_local(Duration::from_secs(1), move || {
-        // Update the button's text
-        b_clone.set_label(&format!("  {}", Local::now().format("%a %d %b %H:%M")));
-
-        // Update the calendar inside the popover
-        p_label.set_markup(&get_calendar_markup());
-
-        glib::ControlFlow::Continue
-    });
+use super::{Context, Module, ModuleBuilder};
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    /// strftime format for the bar label; defaults depend on the bar orientation.
+    pub format: Option<String>,
+}
+
+pub struct Clock {
+    button: Button,
+    popover_label: Label,
+    format: String,
+    source: Option<glib::SourceId>,
+}
+
+impl ModuleBuilder for Clock {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let format = options.format.unwrap_or_else(|| {
+            if ctx.orientation == gtk4::Orientation::Vertical {
+                "\n%H\n%M".to_string()
+            } else {
+                "  %a %d %b %H:%M".to_string()
+            }
+        });
+
+        // 1. Create a Button instead of a Label
+        let button = Button::builder()
+            .label(format_label(&format))
+            .has_frame(false) // Makes it look flat, common for status bars
+            .build();
+
+        button.add_css_class("btn");
+
+        // 2. Setup the Popover
+        let popover = Popover::builder()
+            .position(ctx.popover_position)
+            .autohide(false)
+            .has_arrow(true)
+            .build();
+
+        // Set the button as the parent of the popover
+        popover.set_parent(&button);
+
+        let popover_label = Label::builder()
+            .use_markup(true)
+            .label(get_calendar_markup())
+            .build();
+        popover_label.set_widget_name("popover-label");
+        popover.set_child(Some(&popover_label));
+
+        // 3. Hover logic using EventControllerMotion on the Button
+        let motion_controller = EventControllerMotion::new();
+
+        let p_enter = popover.clone();
+        motion_controller.connect_enter(move |_, _, _| {
+            p_enter.popup();
+        });
+
+        let p_leave = popover.clone();
+        motion_controller.connect_leave(move |_| {
+            p_leave.popdown();
+        });
+
+        button.add_controller(motion_controller);
+
+        Self {
+            button,
+            popover_label,
+            format,
+            source: None,
+        }
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        if let Some(format) = &options.format
+            && StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+        {
+            return Err(format!("invalid time format `{}`", format));
+        }
+        Ok(())
+    }
+}
+
+impl Module for Clock {
+    fn widget(&self) -> gtk4::Widget {
+        self.button.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        // 4. Update loop
+        let p_label = self.popover_label.clone();
+        let b_clone = self.button.clone();
+        let format = self.format.clone();
+        self.source = Some(glib::timeout_add_local(Duration::from_secs(1), move || {
+            // Update the button's text
+            b_clone.set_label(&format_label(&format));
+
+            // Update the calendar inside the popover
+            p_label.set_markup(&get_calendar_markup());
+
+            glib::ControlFlow::Continue
+        }));
+    }
+
+    fn stop(&mut self) {
+        if let Some(source) = self.source.take() {
+            source.remove();
+        }
+    }
+}
+
+fn format_label(format: &str) -> String {
+    Local::now().format(format).to_string()
 }
 
 fn get_calendar_markup() -> String {
@@ -84,7 +147,7 @@ fn get_calendar_markup() -> String {
             if !month_name.len().wrapping_rem(2) == 0 && !lines[0].len().wrapping_rem(20) == 0 {
                 lines[0].push(' ');
             }
-            while lines[0].len() % 22 != 0 {
+            while !lines[0].len().is_multiple_of(22) {
                 lines[0].push(' ');
             }
 
@@ -1,47 +1,56 @@
-use gtk4::Button;
 use gtk4::prelude::*;
+use gtk4::{Button, Label};
+use serde::Deserialize;
 use std::process::Command;
-use std::time::Duration;
-use sysinfo::{Components, System};
-
-pub fn init(container: &gtk4::Box) {
-    let btn = Button::builder().build();
-    btn.add_css_class("btn");
-    container.append(&btn);
-
-    btn.connect_clicked(|_| {
-        let _ = Command::new("footclient").arg("-e").arg("btop").spawn();
-    });
-
-    let mut sys = System::new();
-    let mut components = Components::new();
-
-    glib::timeout_add_local(Duration::from_secs(2), move || {
-        sys.refresh_cpu_usage();
-        sys.refresh_cpu_specifics(sysinfo::CpuRefreshKind::nothing().with_frequency());
-        components.refresh(false);
-
-        // CPU Frequency (max)
-        let max_freq = sys.cpus().iter().map(|c| c.frequency()).max().unwrap_or(0);
-        let ghz = max_freq as f64 / 1000.0;
-
-        // Temperature (from first component for now)
-        let mut temp = 0.0;
-        for c in components.iter() {
-            if c.label().to_lowercase().contains("cpu") || c.label().to_lowercase().contains("core")
-            {
-                temp = c.temperature().unwrap_or(0.0);
-                break;
-            }
-        }
+use std::rc::Rc;
+
+use super::{Context, Event, Module, ModuleBuilder};
+use crate::services::Feed;
+use crate::services::cpu::CpuSample;
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    pub interval: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { interval: 2 }
+    }
+}
+
+pub struct Cpu {
+    btn: Button,
+    feed: Feed<CpuSample>,
+    render: Rc<dyn Fn(&CpuSample)>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Cpu {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let label = Label::builder()
+            .justify(gtk4::Justification::Center)
+            .build();
+        let btn = Button::builder().child(&label).build();
+        btn.add_css_class("btn");
+        let vertical = ctx.orientation == gtk4::Orientation::Vertical;
+
+        btn.connect_clicked(|_| {
+            let _ = Command::new("footclient").arg("-e").arg("btop").spawn();
+        });
 
-        // Unicode bars for each core
-        let bars: String = sys
-            .cpus()
-            .iter()
-            .map(|cpu| {
-                let usage = cpu.cpu_usage();
-                match usage {
+        let render = move |sample: &CpuSample| {
+            let ghz = sample.ghz;
+            let temp = sample.temperature;
+
+            // Unicode bars for each core
+            let bars: Vec<&str> = sample
+                .usage
+                .iter()
+                .map(|&usage| match usage {
                     u if u < 12.5 => " ",
                     u if u < 25.0 => "▂",
                     u if u < 37.5 => "▃",
@@ -50,11 +59,50 @@ pub fn init(container: &gtk4::Box) {
                     u if u < 75.0 => "▆",
                     u if u < 87.5 => "▇",
                     _ => "█",
-                }
-            })
-            .collect();
+                })
+                .collect();
+
+            if vertical {
+                // Stack the readings and wrap the core bars so the label stays narrow
+                let rows: Vec<String> = bars.chunks(4).map(|row| row.concat()).collect();
+                label.set_label(&format!("\n{:.1}\n{:.0}°\n{}", ghz, temp, rows.join("\n")));
+            } else {
+                label.set_label(&format!("  {:.1}GHz {:.0}°C {}", ghz, temp, bars.concat()));
+            }
+        };
+
+        Self {
+            btn,
+            feed: ctx.services.cpu(options.interval),
+            render: Rc::new(render),
+            task: None,
+        }
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        super::validate_interval(options.interval)
+    }
+}
+
+impl Module for Cpu {
+    fn widget(&self) -> gtk4::Widget {
+        self.btn.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        let render = self.render.clone();
+        self.task = Some(self.feed.attach(move |sample| render(sample)));
+    }
 
-        btn.set_label(&format!("  {:.1}GHz {:.0}°C {}", ghz, temp, bars));
-        glib::ControlFlow::Continue
-    });
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event) {
+        if let Event::Refresh = event {
+            self.feed.refresh();
+        }
+    }
 }
@@ -0,0 +1,63 @@
+use gtk4::Button;
+use gtk4::prelude::*;
+use serde::Deserialize;
+
+use super::{Context, Event, Module, ModuleBuilder};
+
+/// A label filled from outside, via `vibebar-p4 msg set-text <id> ...`.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    /// Text shown until something is pushed.
+    pub text: String,
+    pub prefix: String,
+}
+
+pub struct Custom {
+    btn: Button,
+    prefix: String,
+}
+
+impl ModuleBuilder for Custom {
+    type Options = Options;
+
+    fn new(options: Options, _ctx: &Context) -> Self {
+        let btn = Button::new();
+        btn.add_css_class("btn");
+        let custom = Self {
+            btn,
+            prefix: options.prefix,
+        };
+        custom.set_text(&options.text);
+        custom
+    }
+}
+
+impl Custom {
+    /// Shows `text` after the prefix; the module is hidden while it is empty.
+    fn set_text(&self, text: &str) {
+        let text = text.trim();
+        self.btn.set_visible(!text.is_empty());
+        if self.prefix.is_empty() {
+            self.btn.set_label(text);
+        } else {
+            self.btn.set_label(&format!("{} {}", self.prefix, text));
+        }
+    }
+}
+
+impl Module for Custom {
+    fn widget(&self) -> gtk4::Widget {
+        self.btn.clone().upcast()
+    }
+
+    fn start(&mut self) {}
+
+    fn stop(&mut self) {}
+
+    fn handle_event(&mut self, event: &Event) {
+        if let Event::SetText(text) = event {
+            self.set_text(text);
+        }
+    }
+}
@@ -1,30 +1,88 @@
-use glib;
 use gtk4::Button;
 use gtk4::prelude::*;
-use std::time::Duration;
-
-pub fn init(container: &gtk4::Box, path: &str, label_prefix: &str) {
-    let btn = Button::builder()
-        .label(format!("{} ...", label_prefix))
-        .build();
-    btn.add_css_class("btn");
-    container.append(&btn);
-
-    let path_clone = path.to_string();
-    let prefix_clone = label_prefix.to_string();
-    let btn_clone = btn.clone();
-    let update = move || {
-        if let Ok(stat) = nix::sys::statvfs::statvfs(path_clone.as_str()) {
-            let free_bytes = stat.blocks_available() * stat.fragment_size();
-            let free_gb = free_bytes as f64 / (1024.0 * 1024.0 * 1024.0);
-            btn_clone.set_label(&format!("{} {:.1}GB", prefix_clone, free_gb));
+use serde::Deserialize;
+use std::path::Path;
+use std::rc::Rc;
+
+use super::{Context, Event, Module, ModuleBuilder};
+use crate::services::Feed;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Options {
+    pub path: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    60
+}
+
+/// Free GiB, or `None` when the filesystem could not be read.
+type FreeSpace = Option<f64>;
+
+pub struct Disk {
+    btn: Button,
+    feed: Feed<FreeSpace>,
+    render: Rc<dyn Fn(&FreeSpace)>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Disk {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let btn = Button::builder()
+            .label(format!("{} ...", options.prefix))
+            .build();
+        btn.add_css_class("btn");
+
+        let prefix = options.prefix;
+        let btn_clone = btn.clone();
+        let render = move |free_gb: &FreeSpace| {
+            if let Some(free_gb) = free_gb {
+                btn_clone.set_label(&format!("{} {:.1}GB", prefix, free_gb));
+            }
+        };
+
+        Self {
+            btn,
+            feed: ctx.services.disk(&options.path, options.interval),
+            render: Rc::new(render),
+            task: None,
         }
-    };
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        if !Path::new(&options.path).is_absolute() {
+            return Err(format!("path `{}` must be absolute", options.path));
+        }
+        super::validate_interval(options.interval)
+    }
+}
 
-    update();
+impl Module for Disk {
+    fn widget(&self) -> gtk4::Widget {
+        self.btn.clone().upcast()
+    }
 
-    glib::timeout_add_local(Duration::from_secs(60), move || {
-        update();
-        glib::ControlFlow::Continue
-    });
+    fn start(&mut self) {
+        let render = self.render.clone();
+        self.task = Some(self.feed.attach(move |sample| render(sample)));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event) {
+        if let Event::Refresh = event {
+            self.feed.refresh();
+        }
+    }
 }
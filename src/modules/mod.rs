@@ -1,11 +1,171 @@
+use crate::config::ModuleConfig;
+use crate::services::Services;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
 pub mod clock;
 pub mod cpu;
+pub mod custom;
 pub mod disk;
 pub mod gpu;
 pub mod mpris;
 pub mod network;
+pub mod niri;
 pub mod ram;
 pub mod scripts;
 pub mod tray;
+pub mod updates;
 pub mod volume;
+pub mod window;
 pub mod workspaces;
+
+/// Messages delivered to a running module from outside its own update loop.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// Update now instead of waiting for the next poll.
+    Refresh,
+    /// Replace the displayed text; sent to `custom` modules over the control socket.
+    SetText(String),
+}
+
+/// Per-window state handed to every module built for that window.
+pub struct Context {
+    /// Connector name of the output the bar lives on, e.g. `DP-1`.
+    pub monitor: Option<String>,
+    pub tray: Option<Arc<tray::TrayBackend>>,
+    /// The application's runtime; background work is spawned here so it can be
+    /// aborted in `stop`.
+    pub runtime: tokio::runtime::Handle,
+    /// Samplers shared with the modules of every other bar.
+    pub services: Rc<Services>,
+    /// Side popovers open towards, i.e. away from the edge the bar is docked to.
+    pub popover_position: gtk4::PositionType,
+    /// Direction the bar runs in; vertical when docked to a side edge.
+    pub orientation: gtk4::Orientation,
+}
+
+/// A bar widget with its own update loop.
+///
+/// Modules are built once per bar window: the widget is placed into its section,
+/// then `start` is called, and `stop` runs when the window goes away.
+pub trait Module {
+    fn widget(&self) -> gtk4::Widget;
+
+    fn start(&mut self);
+
+    fn stop(&mut self);
+
+    fn handle_event(&mut self, _event: &Event) {}
+}
+
+/// Construction half of a module, kept apart from [`Module`] so the latter stays object safe.
+pub trait ModuleBuilder: Module + Sized + 'static {
+    /// Per-instance options, read from the module's entry in the config.
+    type Options: DeserializeOwned;
+
+    fn new(options: Self::Options, ctx: &Context) -> Self;
+
+    /// Checks options beyond what deserialization already enforces.
+    fn validate(_options: &Self::Options) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+type BuildFn = fn(&toml::Table, &Context) -> Result<Box<dyn Module>, String>;
+
+struct Factory {
+    validate: fn(&toml::Table) -> Result<(), String>,
+    build: BuildFn,
+}
+
+/// Maps the `type` key of a config entry to the module that implements it.
+pub struct Registry {
+    factories: HashMap<String, Factory>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// A registry with every module shipped in this crate.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register::<clock::Clock>("clock");
+        registry.register::<cpu::Cpu>("cpu");
+        registry.register::<custom::Custom>("custom");
+        registry.register::<disk::Disk>("disk");
+        registry.register::<gpu::Gpu>("gpu");
+        registry.register::<mpris::Mpris>("mpris");
+        registry.register::<network::Network>("network");
+        registry.register::<niri::Niri>("niri");
+        registry.register::<ram::Ram>("ram");
+        registry.register::<scripts::Script>("script");
+        registry.register::<tray::Tray>("tray");
+        registry.register::<updates::Updates>("updates");
+        registry.register::<volume::Volume>("volume");
+        registry.register::<window::Window>("window");
+        registry.register::<workspaces::Workspaces>("workspaces");
+        registry
+    }
+
+    pub fn register<M: ModuleBuilder>(&mut self, name: &str) {
+        self.factories.insert(
+            name.to_string(),
+            Factory {
+                validate: |table| parse_options::<M>(table).map(|_| ()),
+                build: |table, ctx| {
+                    let options = parse_options::<M>(table)?;
+                    Ok(Box::new(M::new(options, ctx)))
+                },
+            },
+        );
+    }
+
+    pub fn validate(&self, module: &ModuleConfig) -> Result<(), String> {
+        (self.factory(module)?.validate)(&module.options)
+    }
+
+    pub fn build(&self, module: &ModuleConfig, ctx: &Context) -> Result<Box<dyn Module>, String> {
+        (self.factory(module)?.build)(&module.options, ctx)
+    }
+
+    fn factory(&self, module: &ModuleConfig) -> Result<&Factory, String> {
+        self.factories.get(&module.kind).ok_or_else(|| {
+            let mut known: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            format!(
+                "unknown module type `{}`, expected one of: {}",
+                module.kind,
+                known.join(", ")
+            )
+        })
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_options<M: ModuleBuilder>(table: &toml::Table) -> Result<M::Options, String> {
+    let options: M::Options = toml::Value::Table(table.clone())
+        .try_into()
+        .map_err(|e: toml::de::Error| e.message().to_string())?;
+    M::validate(&options)?;
+    Ok(options)
+}
+
+/// Shared check for the `interval` option of polling modules.
+pub fn validate_interval(secs: u64) -> Result<(), String> {
+    if secs == 0 {
+        Err("interval must be at least 1 second".to_string())
+    } else {
+        Ok(())
+    }
+}
@@ -1,42 +1,62 @@
 use gtk4::Button;
 use gtk4::prelude::*;
-use mpris::PlayerFinder;
-use std::time::Duration;
-
-pub fn init(container: &gtk4::Box) {
-    let btn = Button::builder().label("").build();
-    btn.add_css_class("btn");
-    container.append(&btn);
-
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
-    let btn_clone = btn.clone();
-    gtk4::glib::MainContext::default().spawn_local(async move {
-        while let Some(txt) = rx.recv().await {
-            btn_clone.set_label(&txt);
+use mpris::PlaybackStatus;
+use serde::Deserialize;
+
+use super::{Context, Module, ModuleBuilder};
+use crate::services::Feed;
+use crate::services::mpris::NowPlaying;
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Options {}
+
+pub struct Mpris {
+    btn: Button,
+    feed: Feed<Option<NowPlaying>>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Mpris {
+    type Options = Options;
+
+    fn new(_options: Options, ctx: &Context) -> Self {
+        let btn = Button::builder().label("").build();
+        btn.add_css_class("btn");
+        Self {
+            btn,
+            feed: ctx.services.mpris(),
+            task: None,
         }
-    });
-
-    std::thread::spawn(move || {
-        let finder = PlayerFinder::new().unwrap();
-        loop {
-            if let Ok(player) = finder.find_active() {
-                if let Ok(metadata) = player.get_metadata() {
-                    let artist = metadata.artists().map(|a| a.join(", ")).unwrap_or_default();
-                    let title = metadata.title().unwrap_or_default();
-                    let status = player.get_playback_status().unwrap();
-                    let icon = match status {
-                        mpris::PlaybackStatus::Playing => "",
-                        mpris::PlaybackStatus::Paused => "",
+    }
+}
+
+impl Module for Mpris {
+    fn widget(&self) -> gtk4::Widget {
+        self.btn.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        let btn_clone = self.btn.clone();
+        self.task = Some(self.feed.attach(move |playing| {
+            let text = match playing {
+                Some(playing) => {
+                    let icon = match playing.status {
+                        PlaybackStatus::Playing => "",
+                        PlaybackStatus::Paused => "",
                         _ => "⏹",
                     };
-                    let _ = tx.send(format!("{} {} - {}", icon, artist, title));
-                } else {
-                    let _ = tx.send("".to_string());
+                    format!("{} {} - {}", icon, playing.artist, playing.title)
                 }
-            } else {
-                let _ = tx.send("".to_string());
-            }
-            std::thread::sleep(Duration::from_secs(1));
+                None => "".to_string(),
+            };
+            btn_clone.set_label(&text);
+        }));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
         }
-    });
+    }
 }
@@ -0,0 +1,130 @@
+use gtk4::prelude::*;
+use gtk4::{Button, GestureClick};
+use niri_ipc::{LayoutSwitchTarget, Request};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use tokio::runtime::Handle;
+
+use super::{Context, Module, ModuleBuilder};
+use crate::services::Feed;
+use crate::services::compositor::{State, Status};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// Label template with `{column}`, `{columns}` and `{layout}`.
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Short names for keyboard layouts, by XKB name, e.g. `"English (US)" = "us"`.
+    #[serde(default)]
+    pub layouts: HashMap<String, String>,
+}
+
+fn default_format() -> String {
+    "{column}/{columns} {layout}".to_string()
+}
+
+pub struct Niri {
+    btn: Button,
+    format: String,
+    layouts: HashMap<String, String>,
+    feed: Feed<Status>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Niri {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let btn = Button::new();
+        btn.add_css_class("btn");
+        btn.add_css_class("niri-layout");
+        btn.set_visible(false);
+
+        // Left click goes to the next keyboard layout, right click back
+        let runtime = ctx.runtime.clone();
+        btn.connect_clicked(move |_| switch_layout(&runtime, LayoutSwitchTarget::Next));
+        let right_click = GestureClick::new();
+        right_click.set_button(3);
+        let runtime = ctx.runtime.clone();
+        right_click
+            .connect_pressed(move |_, _, _, _| switch_layout(&runtime, LayoutSwitchTarget::Prev));
+        btn.add_controller(right_click);
+
+        Self {
+            btn,
+            format: options.format,
+            layouts: options.layouts,
+            feed: ctx.services.compositor().0,
+            task: None,
+        }
+    }
+}
+
+impl Module for Niri {
+    fn widget(&self) -> gtk4::Widget {
+        self.btn.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        let btn = self.btn.clone();
+        let format = self.format.clone();
+        let layouts = self.layouts.clone();
+
+        self.task = Some(self.feed.attach(move |status| {
+            // Not running under niri, or lost the connection to it
+            let Status::Connected(State {
+                layout: Some(layout),
+                ..
+            }) = status
+            else {
+                btn.set_visible(false);
+                return;
+            };
+            let keyboard = layout
+                .keyboard_layouts
+                .get(layout.current_layout)
+                .map(String::as_str)
+                .unwrap_or_default();
+            let short = layouts
+                .get(keyboard)
+                .map(String::as_str)
+                .unwrap_or(keyboard);
+            let column = match layout.column {
+                Some(column) => column.to_string(),
+                None => "-".to_string(),
+            };
+            btn.set_label(
+                &format
+                    .replace("{column}", &column)
+                    .replace("{columns}", &layout.columns.to_string())
+                    .replace("{layout}", short),
+            );
+            btn.set_tooltip_text(Some(keyboard).filter(|k| !k.is_empty()));
+            btn.set_visible(true);
+        }));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+fn switch_layout(runtime: &Handle, layout: LayoutSwitchTarget) {
+    runtime.spawn(async move {
+        let action = niri_ipc::Action::SwitchLayout { layout };
+        let reply = async {
+            let mut socket = crate::niri::Socket::connect().await?;
+            socket
+                .send(Request::Action(action))
+                .await?
+                .map_err(io::Error::other)
+        };
+        if let Err(e) = reply.await {
+            eprintln!("vibebar-p4: switching keyboard layout failed: {}", e);
+        }
+    });
+}
@@ -1,27 +1,74 @@
 use gtk4::Button;
 use gtk4::prelude::*;
-use std::time::Duration;
-use sysinfo::System;
-
-pub fn init(container: &gtk4::Box) {
-    let _label = Button::builder().label("  ...%").build();
-    let btn = Button::new();
-    btn.add_css_class("btn");
-    container.append(&btn);
-
-    let mut sys = System::new();
-
-    glib::timeout_add_local(Duration::from_secs(5), move || {
-        sys.refresh_memory();
-        let used = sys.used_memory();
-        let total = sys.total_memory();
-        let perc = if total > 0 {
-            (used as f64 / total as f64) * 100.0
-        } else {
-            0.0
+use serde::Deserialize;
+use std::rc::Rc;
+
+use super::{Context, Event, Module, ModuleBuilder};
+use crate::services::Feed;
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Options {
+    pub interval: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self { interval: 5 }
+    }
+}
+
+pub struct Ram {
+    btn: Button,
+    feed: Feed<f64>,
+    render: Rc<dyn Fn(&f64)>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Ram {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let btn = Button::builder().label("  ...%").build();
+        btn.add_css_class("btn");
+
+        let b = btn.clone();
+        let render = move |perc: &f64| {
+            b.set_label(&format!("  {:.0}%", perc));
         };
 
-        btn.set_label(&format!("  {:.0}%", perc));
-        glib::ControlFlow::Continue
-    });
+        Self {
+            btn,
+            feed: ctx.services.memory(options.interval),
+            render: Rc::new(render),
+            task: None,
+        }
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        super::validate_interval(options.interval)
+    }
+}
+
+impl Module for Ram {
+    fn widget(&self) -> gtk4::Widget {
+        self.btn.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        let render = self.render.clone();
+        self.task = Some(self.feed.attach(move |sample| render(sample)));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event) {
+        if let Event::Refresh = event {
+            self.feed.refresh();
+        }
+    }
 }
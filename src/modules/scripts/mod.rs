@@ -0,0 +1,352 @@
+use gtk4::prelude::*;
+use gtk4::{Button, EventControllerScroll, EventControllerScrollFlags, GestureClick};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+
+use super::{Context, Event, Module, ModuleBuilder};
+use crate::services::Feed;
+use protocol::{Icons, Output};
+use runner::{Polled, Trigger, Update};
+
+mod process;
+mod protocol;
+mod runner;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// Shell command; its stdout (plain text or waybar-style JSON) becomes the label.
+    pub command: String,
+    /// Seconds between runs. Without it the command runs continuously and every
+    /// line it prints is an update.
+    pub interval: Option<u64>,
+    /// Seconds to wait before restarting a continuous command that exited,
+    /// doubled while it keeps dying quickly.
+    pub restart_interval: Option<u64>,
+    /// Seconds after which a run is killed and counted as failed.
+    pub timeout: Option<u64>,
+    /// Also run when the bar receives `SIGRTMIN+signal`, e.g. `pkill -RTMIN+8 vibebar-p4`.
+    pub signal: Option<i32>,
+    /// Keep the last output across restarts; see [`crate::cache`].
+    #[serde(default = "default_cache")]
+    pub cache: bool,
+    /// Run again after an `on-click`/`on-scroll` command finished.
+    #[serde(default = "default_exec_on_event")]
+    pub exec_on_event: bool,
+    #[serde(default)]
+    pub prefix: String,
+    /// Label template with `{}`, `{alt}`, `{icon}` and `{percentage}`.
+    pub format: Option<String>,
+    pub format_icons: Option<Icons>,
+    #[serde(default = "default_tooltip")]
+    pub tooltip: bool,
+    pub on_click: Option<String>,
+    pub on_click_middle: Option<String>,
+    pub on_click_right: Option<String>,
+    pub on_scroll_up: Option<String>,
+    pub on_scroll_down: Option<String>,
+}
+
+fn default_tooltip() -> bool {
+    true
+}
+
+fn default_cache() -> bool {
+    true
+}
+
+fn default_exec_on_event() -> bool {
+    true
+}
+
+pub struct Script {
+    view: Rc<View>,
+    source: Source,
+    runtime: Handle,
+    task: Option<glib::JoinHandle<()>>,
+    worker: Option<tokio::task::JoinHandle<()>>,
+}
+
+/// Where the module's updates come from.
+enum Source {
+    /// Run by one task for every bar polling the same command alike, so it
+    /// runs once per interval no matter how many outputs show it.
+    Polled(Feed<Polled>),
+    /// Started and stopped with the module.
+    Continuous {
+        command: String,
+        restart: Duration,
+        signal: Option<i32>,
+        /// Wakes the worker to restart the command.
+        refresh: Arc<Notify>,
+    },
+}
+
+impl ModuleBuilder for Script {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let button = Button::builder()
+            .label(format!("{} ...", options.prefix))
+            .build();
+
+        button.add_css_class("btn");
+
+        let source = match options.interval {
+            Some(interval) => {
+                let key = format!(
+                    "script:{}:{:?}:{:?}:{}:{}",
+                    interval, options.timeout, options.signal, options.cache, options.command
+                );
+                let runtime = ctx.runtime.clone();
+                let command = options.command.clone();
+                let (timeout, cache, signal) = (options.timeout, options.cache, options.signal);
+                Source::Polled(ctx.services.source(key, move |tx, refresh| {
+                    runtime.spawn(async move {
+                        let trigger = Trigger::new(refresh, signal);
+                        let interval = Duration::from_secs(interval);
+                        let timeout = timeout.map(Duration::from_secs);
+                        runner::poll(command, interval, timeout, cache, trigger, tx).await;
+                    });
+                }))
+            }
+            None => Source::Continuous {
+                command: options.command.clone(),
+                restart: Duration::from_secs(options.restart_interval.unwrap_or(1)),
+                signal: options.signal,
+                refresh: Arc::new(Notify::new()),
+            },
+        };
+        // A continuous command would be restarted, which is not what a click means.
+        let rerun = match &source {
+            Source::Polled(feed) if options.exec_on_event => Some(feed.clone()),
+            _ => None,
+        };
+
+        for (mouse_button, command) in [
+            (1, &options.on_click),
+            (2, &options.on_click_middle),
+            (3, &options.on_click_right),
+        ] {
+            if let Some(command) = command.clone() {
+                let gesture = GestureClick::new();
+                gesture.set_button(mouse_button);
+                let runtime = ctx.runtime.clone();
+                let rerun = rerun.clone();
+                gesture.connect_pressed(move |_, _, _, _| {
+                    run_action(&runtime, &command, rerun.clone())
+                });
+                button.add_controller(gesture);
+            }
+        }
+
+        if options.on_scroll_up.is_some() || options.on_scroll_down.is_some() {
+            let scroll = EventControllerScroll::new(EventControllerScrollFlags::VERTICAL);
+            let runtime = ctx.runtime.clone();
+            let up = options.on_scroll_up.clone();
+            let down = options.on_scroll_down.clone();
+            let rerun = rerun.clone();
+            scroll.connect_scroll(move |_, _, dy| {
+                let command = if dy < 0.0 { &up } else { &down };
+                if let Some(command) = command {
+                    run_action(&runtime, command, rerun.clone());
+                }
+                glib::Propagation::Stop
+            });
+            button.add_controller(scroll);
+        }
+
+        // The prefix predates `format` and is kept in front of it.
+        let format = options.format.unwrap_or_else(|| "{}".to_string());
+        let format = if options.prefix.is_empty() {
+            format
+        } else {
+            format!("{} {}", options.prefix, format)
+        };
+
+        Self {
+            view: Rc::new(View {
+                button,
+                format,
+                icons: options.format_icons,
+                tooltip: options.tooltip,
+                classes: RefCell::new(Vec::new()),
+                last: RefCell::new(None),
+            }),
+            source,
+            runtime: ctx.runtime.clone(),
+            task: None,
+            worker: None,
+        }
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        if options.command.trim().is_empty() {
+            return Err("command must not be empty".to_string());
+        }
+        if let Some(signal) = options.signal {
+            let max = nix::libc::SIGRTMAX() - nix::libc::SIGRTMIN();
+            if !(1..=max).contains(&signal) {
+                return Err(format!("signal must be between 1 and {}", max));
+            }
+        }
+        if options.timeout == Some(0) {
+            return Err("timeout must be at least 1 second".to_string());
+        }
+        if options.interval.is_none() && options.timeout.is_some() {
+            return Err("timeout only applies to scripts with an interval".to_string());
+        }
+        match (options.interval, options.restart_interval) {
+            (Some(_), Some(_)) => Err(
+                "restart-interval only applies to continuous scripts, i.e. without interval"
+                    .to_string(),
+            ),
+            (Some(interval), None) => super::validate_interval(interval),
+            (None, Some(restart)) => super::validate_interval(restart)
+                .map_err(|_| "restart-interval must be at least 1 second".to_string()),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl Module for Script {
+    fn widget(&self) -> gtk4::Widget {
+        self.view.button.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        let view = self.view.clone();
+        match &self.source {
+            Source::Polled(feed) => {
+                self.task = Some(feed.attach(move |polled| {
+                    for update in polled.updates() {
+                        view.apply(update);
+                    }
+                }));
+            }
+            Source::Continuous {
+                command,
+                restart,
+                signal,
+                refresh,
+            } => {
+                let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Update>();
+                self.task = Some(gtk4::glib::MainContext::default().spawn_local(async move {
+                    while let Some(update) = rx.recv().await {
+                        view.apply(update);
+                    }
+                }));
+
+                let command = command.clone();
+                let (restart, signal, refresh) = (*restart, *signal, refresh.clone());
+                self.worker = Some(self.runtime.spawn(async move {
+                    let trigger = Trigger::new(refresh, signal);
+                    runner::follow(&command, restart, trigger, tx).await;
+                }));
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        if let Some(worker) = self.worker.take() {
+            worker.abort();
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event) {
+        if let Event::Refresh = event {
+            match &self.source {
+                Source::Polled(feed) => feed.refresh(),
+                Source::Continuous { refresh, .. } => refresh.notify_one(),
+            }
+        }
+    }
+}
+
+/// The button and what it last showed.
+struct View {
+    button: Button,
+    format: String,
+    icons: Option<Icons>,
+    tooltip: bool,
+    /// Classes set by the previous update, removed before applying new ones
+    classes: RefCell<Vec<String>>,
+    /// Last successful output, kept on screen while later runs fail
+    last: RefCell<Option<Output>>,
+}
+
+impl View {
+    fn apply(&self, update: Update) {
+        let b = &self.button;
+        match update {
+            Update::Output(output) => {
+                b.remove_css_class("error");
+                b.remove_css_class("stale");
+                // Like waybar, an empty text hides the module
+                b.set_visible(!output.text.is_empty());
+                b.set_label(&protocol::format(
+                    &self.format,
+                    self.icons.as_ref(),
+                    &output,
+                ));
+                if self.tooltip {
+                    b.set_tooltip_text(output.tooltip.as_deref());
+                }
+
+                let mut classes = self.classes.borrow_mut();
+                for class in classes.iter() {
+                    b.remove_css_class(class);
+                }
+                for class in &output.classes {
+                    b.add_css_class(class);
+                }
+                *classes = output.classes.clone();
+                *self.last.borrow_mut() = Some(output);
+            }
+            Update::Failed(reason) => {
+                let last = self.last.borrow();
+                b.add_css_class("error");
+                if last.is_some() {
+                    b.add_css_class("stale");
+                }
+                let previous = last.as_ref().and_then(|o| o.tooltip.as_deref());
+                let message = match previous {
+                    Some(previous) if self.tooltip => {
+                        format!("{}\n\nLast update failed: {}", previous, reason)
+                    }
+                    _ => format!("Last update failed: {}", reason),
+                };
+                b.set_tooltip_text(Some(&message));
+            }
+        }
+    }
+}
+
+/// Runs an `on-click`/`on-scroll` command in the background, then refreshes
+/// `rerun` once it finished.
+fn run_action(runtime: &Handle, command: &str, rerun: Option<Feed<Polled>>) {
+    let child = {
+        let _guard = runtime.enter();
+        Command::new("sh").arg("-c").arg(command).spawn()
+    };
+    match child {
+        Ok(mut child) => {
+            runtime.spawn(async move {
+                let _ = child.wait().await;
+                if let Some(rerun) = rerun {
+                    rerun.refresh();
+                }
+            });
+        }
+        Err(e) => eprintln!("vibebar-p4: failed to run `{}`: {}", command, e),
+    }
+}
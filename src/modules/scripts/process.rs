@@ -0,0 +1,62 @@
+use nix::sys::signal::{Signal, killpg};
+use nix::unistd::Pid;
+use std::io;
+use std::process::{ExitStatus, Stdio};
+use tokio::io::AsyncReadExt;
+use tokio::process::{ChildStdout, Command};
+
+/// A script started with `sh -c` in a process group of its own.
+///
+/// Dropping it before it exited kills the whole group, so the other commands
+/// of a pipeline do not outlive the module that started them.
+pub struct Process {
+    child: tokio::process::Child,
+    running: bool,
+}
+
+impl Process {
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()?;
+        Ok(Self {
+            child,
+            running: true,
+        })
+    }
+
+    /// The script's stdout; can be taken once.
+    pub fn stdout(&mut self) -> Option<ChildStdout> {
+        self.child.stdout.take()
+    }
+
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        let status = self.child.wait().await;
+        self.running = false;
+        status
+    }
+
+    /// Waits for the script to exit and returns everything it printed.
+    pub async fn output(&mut self) -> io::Result<(ExitStatus, String)> {
+        let mut stdout = String::new();
+        if let Some(mut pipe) = self.stdout() {
+            pipe.read_to_string(&mut stdout).await?;
+        }
+        Ok((self.wait().await?, stdout))
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        if self.running
+            && let Some(pid) = self.child.id()
+        {
+            let _ = killpg(Pid::from_raw(pid as i32), Signal::SIGTERM);
+        }
+    }
+}
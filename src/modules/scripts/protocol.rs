@@ -0,0 +1,125 @@
+//! Waybar's `custom` module protocol: what scripts print, and how it is displayed.
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// One update printed by a script.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Output {
+    pub text: String,
+    pub alt: Option<String>,
+    pub tooltip: Option<String>,
+    /// CSS classes for the module's button.
+    pub classes: Vec<String>,
+    pub percentage: Option<u32>,
+}
+
+impl Output {
+    /// Parses a JSON object, or plain text where the first three lines are the
+    /// text, the tooltip and the class.
+    pub fn parse(stdout: &str) -> Self {
+        match serde_json::from_str::<serde_json::Value>(stdout) {
+            Ok(serde_json::Value::Object(json)) => Self::from_json(&json),
+            _ => Self::from_lines(stdout),
+        }
+    }
+
+    fn from_json(json: &serde_json::Map<String, serde_json::Value>) -> Self {
+        let string = |key: &str| match json.get(key)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
+        };
+        let classes = match json.get("class") {
+            Some(serde_json::Value::String(class)) => vec![class.clone()],
+            Some(serde_json::Value::Array(classes)) => classes
+                .iter()
+                .filter_map(|c| c.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self {
+            text: string("text").unwrap_or_default(),
+            alt: string("alt"),
+            tooltip: string("tooltip"),
+            classes: classes.into_iter().filter(|c| !c.is_empty()).collect(),
+            percentage: json
+                .get("percentage")
+                .and_then(|p| p.as_f64())
+                .map(|p| p.round().clamp(0.0, 100.0) as u32),
+        }
+    }
+
+    fn from_lines(stdout: &str) -> Self {
+        let mut lines = stdout.lines().map(str::trim);
+        let text = lines.next().unwrap_or_default().to_string();
+        let tooltip = lines.next().filter(|l| !l.is_empty()).map(str::to_string);
+        let classes = lines
+            .next()
+            .filter(|l| !l.is_empty())
+            .map(|class| vec![class.to_string()])
+            .unwrap_or_default();
+        Self {
+            text,
+            tooltip,
+            classes,
+            ..Self::default()
+        }
+    }
+}
+
+/// The `format-icons` option: a list indexed by `percentage`, or icons keyed by `alt`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Icons {
+    Ramp(Vec<String>),
+    ByAlt(HashMap<String, String>),
+}
+
+impl Icons {
+    fn pick(&self, output: &Output) -> &str {
+        match self {
+            Icons::Ramp(icons) if !icons.is_empty() => {
+                let percentage = output.percentage.unwrap_or(0) as usize;
+                let index = (percentage * icons.len() / 100).min(icons.len() - 1);
+                &icons[index]
+            }
+            Icons::Ramp(_) => "",
+            Icons::ByAlt(icons) => output
+                .alt
+                .as_ref()
+                .and_then(|alt| icons.get(alt))
+                .or_else(|| icons.get("default"))
+                .map(String::as_str)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Expands `{}`/`{text}`, `{alt}`, `{icon}` and `{percentage}` in `format`;
+/// anything else in braces is kept as is.
+pub fn format(format: &str, icons: Option<&Icons>, output: &Output) -> String {
+    let mut formatted = String::with_capacity(format.len() + output.text.len());
+    let mut rest = format;
+    while let Some(open) = rest.find('{') {
+        formatted.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let placeholder = rest;
+        let Some(close) = placeholder.find('}') else {
+            break;
+        };
+        match &placeholder[1..close] {
+            "" | "text" => formatted.push_str(&output.text),
+            "alt" => formatted.push_str(output.alt.as_deref().unwrap_or_default()),
+            "icon" => formatted.push_str(icons.map(|i| i.pick(output)).unwrap_or_default()),
+            "percentage" => {
+                if let Some(percentage) = output.percentage {
+                    formatted.push_str(&percentage.to_string());
+                }
+            }
+            _ => formatted.push_str(&placeholder[..=close]),
+        }
+        rest = &placeholder[close + 1..];
+    }
+    formatted.push_str(rest);
+    formatted
+}
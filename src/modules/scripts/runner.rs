@@ -0,0 +1,226 @@
+//! The async side of a script module: running the command and reporting what it printed.
+use super::process::Process;
+use super::protocol::Output;
+use crate::cache::Cache;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::signal::unix::{Signal, SignalKind, signal};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{Notify, watch};
+
+/// A continuous command that ran at least this long is considered healthy again.
+const HEALTHY_RUN: Duration = Duration::from_secs(60);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(300);
+
+pub enum Update {
+    Output(Output),
+    /// The last run failed; the previous output, if any, is now stale.
+    Failed(String),
+}
+
+/// What a polled command last reported, shared by every bar showing it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polled {
+    /// The last successful run's output.
+    pub output: Option<Output>,
+    /// Why the latest run failed, unless it succeeded.
+    pub error: Option<String>,
+}
+
+impl Polled {
+    /// The updates that bring a freshly built module to this state.
+    pub fn updates(&self) -> impl Iterator<Item = Update> {
+        let output = self.output.clone().map(Update::Output);
+        let error = self.error.clone().map(Update::Failed);
+        output.into_iter().chain(error)
+    }
+}
+
+/// Requests to run the command again ahead of time.
+pub struct Trigger {
+    notify: Arc<Notify>,
+    signal: Option<Signal>,
+}
+
+impl Trigger {
+    /// Listens to `notify`, and to `SIGRTMIN+offset` when given.
+    pub fn new(notify: Arc<Notify>, offset: Option<i32>) -> Self {
+        let signal = offset.and_then(|offset| {
+            let number = nix::libc::SIGRTMIN() + offset;
+            signal(SignalKind::from_raw(number))
+                .inspect_err(|e| eprintln!("vibebar-p4: cannot listen for RTMIN+{}: {}", offset, e))
+                .ok()
+        });
+        Self { notify, signal }
+    }
+
+    async fn fired(&mut self) {
+        match &mut self.signal {
+            Some(signal) => {
+                tokio::select! {
+                    _ = self.notify.notified() => {}
+                    _ = signal.recv() => {}
+                }
+            }
+            None => self.notify.notified().await,
+        }
+    }
+}
+
+/// Runs `command` every `interval` for as long as the runtime lives, idling
+/// while no bar shows it.
+pub async fn poll(
+    command: String,
+    interval: Duration,
+    timeout: Option<Duration>,
+    cache: bool,
+    mut trigger: Trigger,
+    tx: watch::Sender<Option<Polled>>,
+) {
+    let cache = cache.then(|| Cache::new("script", &command));
+    let mut polled = Polled::default();
+
+    // Show the cached output at once, and only run when it is due anyway.
+    if let Some(cache) = &cache
+        && let Some((stdout, age)) = cache.load::<String>().await
+    {
+        polled.output = Some(Output::parse(stdout.trim()));
+        tx.send_replace(Some(polled.clone()));
+        if let Some(remaining) = interval.checked_sub(age) {
+            tokio::select! {
+                _ = tokio::time::sleep(remaining) => {}
+                _ = trigger.fired() => {}
+            }
+        }
+    }
+
+    loop {
+        if tx.receiver_count() > 0 {
+            match run_once(&command, timeout).await {
+                Ok(stdout) => {
+                    if let Some(cache) = &cache {
+                        cache.store(&stdout).await;
+                    }
+                    polled = Polled {
+                        output: Some(Output::parse(stdout.trim())),
+                        error: None,
+                    };
+                }
+                Err(e) => {
+                    eprintln!("vibebar-p4: script `{}` failed: {}", command, e);
+                    polled.error = Some(e.to_string());
+                }
+            }
+            tx.send_replace(Some(polled.clone()));
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = trigger.fired() => {}
+        }
+    }
+}
+
+/// Runs `command` to completion; a non-zero exit or running past `timeout` is an error.
+async fn run_once(command: &str, timeout: Option<Duration>) -> io::Result<String> {
+    let mut process = Process::spawn(command)?;
+    let (status, stdout) = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, process.output())
+            .await
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("timed out after {}s", timeout.as_secs()),
+                )
+            })??,
+        None => process.output().await?,
+    };
+    if status.success() {
+        Ok(stdout)
+    } else {
+        Err(io::Error::other(status.to_string()))
+    }
+}
+
+/// How one run of a continuous command ended.
+enum Ended {
+    Exited(String),
+    /// Killed because a refresh was requested.
+    Restarted,
+    /// The module is gone.
+    Closed,
+}
+
+/// Runs `command` for as long as the module lives, one update per output line.
+/// A trigger restarts it.
+pub async fn follow(
+    command: &str,
+    restart: Duration,
+    mut trigger: Trigger,
+    tx: UnboundedSender<Update>,
+) {
+    let mut delay = restart;
+    loop {
+        let started = Instant::now();
+        let reason = match stream(command, &mut trigger, &tx).await {
+            Ended::Exited(reason) => reason,
+            Ended::Restarted => {
+                delay = restart;
+                continue;
+            }
+            Ended::Closed => return,
+        };
+
+        if tx
+            .send(Update::Failed(format!("exited: {}", reason)))
+            .is_err()
+        {
+            return;
+        }
+        // Back off while the command keeps dying right after starting.
+        if started.elapsed() >= HEALTHY_RUN {
+            delay = restart;
+        }
+        eprintln!(
+            "vibebar-p4: script `{}` stopped ({}), restarting in {}s",
+            command,
+            reason,
+            delay.as_secs()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = trigger.fired() => {}
+        }
+        delay = (delay * 2).min(MAX_RESTART_DELAY.max(restart));
+    }
+}
+
+async fn stream(command: &str, trigger: &mut Trigger, tx: &UnboundedSender<Update>) -> Ended {
+    let mut process = match Process::spawn(command) {
+        Ok(process) => process,
+        Err(e) => return Ended::Exited(e.to_string()),
+    };
+    if let Some(stdout) = process.stdout() {
+        let mut lines = BufReader::new(stdout).lines();
+        loop {
+            tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => {
+                        if tx.send(Update::Output(Output::parse(line.trim()))).is_err() {
+                            return Ended::Closed;
+                        }
+                    }
+                    _ => break,
+                },
+                // Dropping the process kills it
+                _ = trigger.fired() => return Ended::Restarted,
+            }
+        }
+    }
+    match process.wait().await {
+        Ok(status) => Ended::Exited(status.to_string()),
+        Err(e) => Ended::Exited(e.to_string()),
+    }
+}
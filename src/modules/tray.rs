@@ -1,11 +1,16 @@
 use gtk4::prelude::*;
 use gtk4::{Box, Button, GestureClick, Image, Orientation, Popover};
+use serde::Deserialize;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use system_tray::client::{ActivateRequest, Client, UpdateEvent};
 use system_tray::menu::TrayMenu;
 use tokio::sync::{broadcast, mpsc};
 
+use super::{Context, Module, ModuleBuilder};
+
 #[derive(Clone, Debug)]
 pub struct TrayItemInfo {
     pub icon_name: Option<String>,
@@ -201,115 +206,162 @@ impl TrayBackend {
     }
 }
 
-pub fn init(container: &gtk4::Box, backend: Arc<TrayBackend>) {
-    let tray_box = Box::new(Orientation::Horizontal, 0);
-    tray_box.set_widget_name("tray");
-    container.append(&tray_box);
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Options {}
 
-    let gui_items: Arc<Mutex<HashMap<String, gtk4::Widget>>> = Arc::new(Mutex::new(HashMap::new()));
+pub struct Tray {
+    tray_box: Box,
+    backend: Option<Arc<TrayBackend>>,
+    popover_position: gtk4::PositionType,
+    task: Option<glib::JoinHandle<()>>,
+}
 
-    let tbox = tray_box.clone();
-    let tgui_items = gui_items.clone();
-    let backend_ui = backend.clone();
-    let mut rx_ui_global = backend.tx_ui.subscribe();
+impl ModuleBuilder for Tray {
+    type Options = Options;
 
-    gtk4::glib::MainContext::default().spawn_local(async move {
-        // Initial Sync Logic
-        {
-            let items = backend_ui.titems.lock().unwrap();
-            let mut map = tgui_items.lock().unwrap();
-            for (id, info) in items.iter() {
-                let btn = Button::builder()
-                    .css_classes(vec!["btn".to_string()])
-                    .build();
-                let is_visible = matches!(
-                    info.status,
-                    system_tray::item::Status::Active | system_tray::item::Status::NeedsAttention
-                );
-                btn.set_visible(is_visible);
-                update_icon(&btn, info);
-                setup_button_signals(&btn, id, &backend_ui);
-                tbox.append(&btn);
-                map.insert(id.clone(), btn.upcast());
-            }
+    fn new(_options: Options, ctx: &Context) -> Self {
+        let tray_box = Box::new(ctx.orientation, 0);
+        tray_box.set_widget_name("tray");
+
+        Self {
+            tray_box,
+            backend: ctx.tray.clone(),
+            popover_position: ctx.popover_position,
+            task: None,
         }
+    }
+}
+
+impl Module for Tray {
+    fn widget(&self) -> gtk4::Widget {
+        self.tray_box.clone().upcast()
+    }
 
-        loop {
-            match rx_ui_global.recv().await {
-                Ok((id, info_opt)) => {
-                    let mut map = tgui_items.lock().unwrap();
-                    if let Some(info) = info_opt {
-                        let is_visible = matches!(
-                            info.status,
-                            system_tray::item::Status::Active
-                                | system_tray::item::Status::NeedsAttention
-                        );
-
-                        if let Some(widget) = map.get(&id) {
-                            if let Some(btn) = widget.downcast_ref::<Button>() {
+    fn start(&mut self) {
+        let Some(backend) = self.backend.clone() else {
+            return;
+        };
+        let popover_position = self.popover_position;
+
+        let gui_items: Rc<RefCell<HashMap<String, gtk4::Widget>>> =
+            Rc::new(RefCell::new(HashMap::new()));
+
+        let tbox = self.tray_box.clone();
+        let tgui_items = gui_items.clone();
+        let backend_ui = backend.clone();
+        let mut rx_ui_global = backend.tx_ui.subscribe();
+
+        self.task = Some(gtk4::glib::MainContext::default().spawn_local(async move {
+            // Initial Sync Logic
+            {
+                let items = backend_ui.titems.lock().unwrap();
+                let mut map = tgui_items.borrow_mut();
+                for (id, info) in items.iter() {
+                    let btn = Button::builder()
+                        .css_classes(vec!["btn".to_string()])
+                        .build();
+                    let is_visible = matches!(
+                        info.status,
+                        system_tray::item::Status::Active
+                            | system_tray::item::Status::NeedsAttention
+                    );
+                    btn.set_visible(is_visible);
+                    update_icon(&btn, info);
+                    setup_button_signals(&btn, id, &backend_ui, popover_position);
+                    tbox.append(&btn);
+                    map.insert(id.clone(), btn.upcast());
+                }
+            }
+
+            loop {
+                match rx_ui_global.recv().await {
+                    Ok((id, info_opt)) => {
+                        let mut map = tgui_items.borrow_mut();
+                        if let Some(info) = info_opt {
+                            let is_visible = matches!(
+                                info.status,
+                                system_tray::item::Status::Active
+                                    | system_tray::item::Status::NeedsAttention
+                            );
+
+                            if let Some(widget) = map.get(&id) {
+                                if let Some(btn) = widget.downcast_ref::<Button>() {
+                                    btn.set_visible(is_visible);
+                                    update_icon(btn, &info);
+                                }
+                            } else {
+                                let btn = Button::builder()
+                                    .css_classes(vec!["btn".to_string()])
+                                    .build();
                                 btn.set_visible(is_visible);
-                                update_icon(btn, &info);
+                                update_icon(&btn, &info);
+                                setup_button_signals(&btn, &id, &backend_ui, popover_position);
+                                tbox.append(&btn);
+                                map.insert(id, btn.upcast());
                             }
                         } else {
-                            let btn = Button::builder()
-                                .css_classes(vec!["btn".to_string()])
-                                .build();
-                            btn.set_visible(is_visible);
-                            update_icon(&btn, &info);
-                            setup_button_signals(&btn, &id, &backend_ui);
-                            tbox.append(&btn);
-                            map.insert(id, btn.upcast());
-                        }
-                    } else {
-                        if let Some(widget) = map.remove(&id) {
-                            tbox.remove(&widget);
+                            if let Some(widget) = map.remove(&id) {
+                                tbox.remove(&widget);
+                            }
                         }
                     }
-                }
-                Err(broadcast::error::RecvError::Lagged(_)) => {
-                    // Full reconciliation on lag
-                    let items = backend_ui.titems.lock().unwrap();
-                    let mut map = tgui_items.lock().unwrap();
-
-                    map.retain(|id, widget| {
-                        if !items.contains_key(id) {
-                            tbox.remove(widget);
-                            false
-                        } else {
-                            true
-                        }
-                    });
-
-                    for (id, info) in items.iter() {
-                        let is_visible = matches!(
-                            info.status,
-                            system_tray::item::Status::Active
-                                | system_tray::item::Status::NeedsAttention
-                        );
-                        if let Some(widget) = map.get(id) {
-                            if let Some(btn) = widget.downcast_ref::<Button>() {
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        // Full reconciliation on lag
+                        let items = backend_ui.titems.lock().unwrap();
+                        let mut map = tgui_items.borrow_mut();
+
+                        map.retain(|id, widget| {
+                            if !items.contains_key(id) {
+                                tbox.remove(widget);
+                                false
+                            } else {
+                                true
+                            }
+                        });
+
+                        for (id, info) in items.iter() {
+                            let is_visible = matches!(
+                                info.status,
+                                system_tray::item::Status::Active
+                                    | system_tray::item::Status::NeedsAttention
+                            );
+                            if let Some(widget) = map.get(id) {
+                                if let Some(btn) = widget.downcast_ref::<Button>() {
+                                    btn.set_visible(is_visible);
+                                    update_icon(btn, info);
+                                }
+                            } else {
+                                let btn = Button::builder()
+                                    .css_classes(vec!["btn".to_string()])
+                                    .build();
                                 btn.set_visible(is_visible);
-                                update_icon(btn, info);
+                                update_icon(&btn, info);
+                                setup_button_signals(&btn, id, &backend_ui, popover_position);
+                                tbox.append(&btn);
+                                map.insert(id.clone(), btn.upcast());
                             }
-                        } else {
-                            let btn = Button::builder()
-                                .css_classes(vec!["btn".to_string()])
-                                .build();
-                            btn.set_visible(is_visible);
-                            update_icon(&btn, info);
-                            setup_button_signals(&btn, id, &backend_ui);
-                            tbox.append(&btn);
-                            map.insert(id.clone(), btn.upcast());
                         }
                     }
+                    Err(broadcast::error::RecvError::Closed) => break,
                 }
-                Err(broadcast::error::RecvError::Closed) => break,
             }
+        }));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
         }
-    });
+    }
 }
 
-fn setup_button_signals(btn: &Button, id: &str, backend: &Arc<TrayBackend>) {
+fn setup_button_signals(
+    btn: &Button,
+    id: &str,
+    backend: &Arc<TrayBackend>,
+    popover_position: gtk4::PositionType,
+) {
     let id_clone = id.to_string();
     let tx_act = backend.tx_activate.clone();
     let btn_handle = btn.clone();
@@ -359,7 +411,7 @@ fn setup_button_signals(btn: &Button, id: &str, backend: &Arc<TrayBackend>) {
 
         if let Some((menu_path, menu)) = menu_opt {
             let popover = Popover::builder()
-                .position(gtk4::PositionType::Top)
+                .position(popover_position)
                 .autohide(true)
                 .has_arrow(true)
                 .build();
@@ -371,6 +423,7 @@ fn setup_button_signals(btn: &Button, id: &str, backend: &Arc<TrayBackend>) {
                 menu_path,
                 tx_act_right.clone(),
                 Some(popover.clone()),
+                submenu_position(popover_position),
             );
             popover.set_child(Some(&vbox));
             popover.popup();
@@ -381,10 +434,10 @@ fn setup_button_signals(btn: &Button, id: &str, backend: &Arc<TrayBackend>) {
 
 fn update_icon(btn: &Button, info: &TrayItemInfo) {
     let theme = gtk4::IconTheme::for_display(&gtk4::gdk::Display::default().unwrap());
-    if let Some(path) = &info.icon_theme_path {
-        if !theme.search_path().iter().any(|p| p.to_str() == Some(path)) {
-            theme.add_search_path(path);
-        }
+    if let Some(path) = &info.icon_theme_path
+        && !theme.search_path().iter().any(|p| p.to_str() == Some(path))
+    {
+        theme.add_search_path(path);
     }
 
     if let Some(name) = &info.icon_name {
@@ -427,6 +480,7 @@ fn create_menu_vbox(
     menu_path: String,
     tx_activate: mpsc::UnboundedSender<TrayAction>,
     parent_popover: Option<Popover>,
+    submenu_position: gtk4::PositionType,
 ) -> Box {
     let vbox = Box::new(Orientation::Vertical, 0);
     for item in items {
@@ -460,7 +514,7 @@ fn create_menu_vbox(
 
             item_btn.connect_clicked(move |_| {
                 let sub_pop = Popover::builder()
-                    .position(gtk4::PositionType::Right)
+                    .position(submenu_position)
                     .autohide(true)
                     .build();
                 sub_pop.set_parent(&btn_sub);
@@ -470,6 +524,7 @@ fn create_menu_vbox(
                     path_sub.clone(),
                     tx_sub.clone(),
                     Some(sub_pop.clone()),
+                    submenu_position,
                 );
                 sub_pop.set_child(Some(&sub_vbox));
                 sub_pop.popup();
@@ -495,3 +550,12 @@ fn create_menu_vbox(
     }
     vbox
 }
+
+/// Submenus open sideways, away from the screen edge when the bar is docked on the right.
+fn submenu_position(popover_position: gtk4::PositionType) -> gtk4::PositionType {
+    if popover_position == gtk4::PositionType::Left {
+        gtk4::PositionType::Left
+    } else {
+        gtk4::PositionType::Right
+    }
+}
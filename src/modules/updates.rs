@@ -0,0 +1,227 @@
+use gtk4::prelude::*;
+use gtk4::{
+    Box, Button, EventControllerMotion, GestureClick, Label, Orientation, Popover, ScrolledWindow,
+};
+use serde::Deserialize;
+use std::rc::Rc;
+use tokio::process::Command;
+
+use super::{Context, Event, Module, ModuleBuilder};
+use crate::services::Feed;
+use crate::services::updates::{Backend, Package, Updates as Pending};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    #[serde(default)]
+    pub backend: Backend,
+    /// Replaces the backend's command, keeping its output format; e.g.
+    /// `paru -Qua` for pacman or the path to `nix`.
+    pub command: Option<String>,
+    /// Flake directory checked by the `nix` backend.
+    #[serde(default = "default_flake")]
+    pub flake: String,
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    #[serde(default)]
+    pub prefix: String,
+    /// Run on click, e.g. a terminal with the upgrade; the count is checked
+    /// again once it exits.
+    pub on_click: Option<String>,
+}
+
+fn default_flake() -> String {
+    "/etc/nixos".to_string()
+}
+
+fn default_interval() -> u64 {
+    3600
+}
+
+pub struct Updates {
+    btn: Button,
+    feed: Feed<Pending>,
+    render: Rc<dyn Fn(&Pending)>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Updates {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let btn = Button::builder()
+            .label(label(&options.prefix, "..."))
+            .build();
+        btn.add_css_class("btn");
+
+        let popover = Popover::builder()
+            .position(ctx.popover_position)
+            .autohide(false)
+            .has_arrow(true)
+            .build();
+        popover.set_parent(&btn);
+
+        let popover_vbox = Box::new(Orientation::Vertical, 4);
+        popover_vbox.set_margin_start(10);
+        popover_vbox.set_margin_end(10);
+        popover_vbox.set_margin_top(10);
+        popover_vbox.set_margin_bottom(10);
+        popover.set_child(Some(&popover_vbox));
+
+        let pop_title = Label::builder().halign(gtk4::Align::Start).build();
+        let pop_list = Label::builder()
+            .halign(gtk4::Align::Start)
+            .valign(gtk4::Align::Start)
+            .build();
+        pop_title.add_css_class("updates-popover-title");
+        pop_list.add_css_class("updates-popover-list");
+
+        // Long lists scroll instead of growing past the popup space
+        let scroller = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .propagate_natural_width(true)
+            .propagate_natural_height(true)
+            .max_content_height(400)
+            .child(&pop_list)
+            .build();
+
+        popover_vbox.append(&pop_title);
+        popover_vbox.append(&scroller);
+
+        let feed = ctx.services.updates(
+            options.backend,
+            options.command.clone(),
+            &options.flake,
+            options.interval,
+        );
+
+        if let Some(command) = options.on_click {
+            let click_gesture = GestureClick::new();
+            click_gesture.set_button(1);
+            let runtime = ctx.runtime.clone();
+            let feed = feed.clone();
+            click_gesture.connect_pressed(move |_, _, _, _| {
+                let child = {
+                    let _guard = runtime.enter();
+                    Command::new("sh").arg("-c").arg(&command).spawn()
+                };
+                match child {
+                    Ok(mut child) => {
+                        let feed = feed.clone();
+                        runtime.spawn(async move {
+                            let _ = child.wait().await;
+                            feed.refresh();
+                        });
+                    }
+                    Err(e) => eprintln!("vibebar-p4: failed to run `{}`: {}", command, e),
+                }
+            });
+            btn.add_controller(click_gesture);
+        }
+
+        // Hover gesture for popover
+        let motion_controller = EventControllerMotion::new();
+        let p_enter = popover.clone();
+        motion_controller.connect_enter(move |_, _, _| {
+            p_enter.popup();
+        });
+        let p_leave = popover.clone();
+        motion_controller.connect_leave(move |_| {
+            p_leave.popdown();
+        });
+        btn.add_controller(motion_controller);
+
+        let prefix = options.prefix;
+        let btn_clone = btn.clone();
+        let render = move |pending: &Pending| match pending {
+            Ok(packages) => {
+                btn_clone.remove_css_class("error");
+                btn_clone.set_label(&label(&prefix, &packages.len().to_string()));
+                if packages.is_empty() {
+                    btn_clone.remove_css_class("pending");
+                    pop_title.set_markup("<b>Up to date</b>");
+                    scroller.set_visible(false);
+                } else {
+                    btn_clone.add_css_class("pending");
+                    pop_title.set_markup(&format!("<b>{} updates</b>", packages.len()));
+                    pop_list.set_text(&list(packages));
+                    scroller.set_visible(true);
+                }
+            }
+            Err(reason) => {
+                btn_clone.add_css_class("error");
+                btn_clone.set_label(&label(&prefix, "?"));
+                pop_title.set_markup("<b>Update check failed</b>");
+                pop_list.set_text(reason);
+                scroller.set_visible(true);
+            }
+        };
+
+        Self {
+            btn,
+            feed,
+            render: Rc::new(render),
+            task: None,
+        }
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        if options
+            .command
+            .as_deref()
+            .is_some_and(|c| c.trim().is_empty())
+        {
+            return Err("command must not be empty".to_string());
+        }
+        super::validate_interval(options.interval)
+    }
+}
+
+impl Module for Updates {
+    fn widget(&self) -> gtk4::Widget {
+        self.btn.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        let render = self.render.clone();
+        self.task = Some(self.feed.attach(move |pending| render(pending)));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event) {
+        if let Event::Refresh = event {
+            self.feed.refresh();
+        }
+    }
+}
+
+/// One `name old → new` line per package, with the names padded into a column.
+fn list(packages: &[Package]) -> String {
+    let width = packages
+        .iter()
+        .map(|p| p.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    packages
+        .iter()
+        .map(|p| match &p.old {
+            Some(old) => format!("{:width$}  {} → {}", p.name, old, p.new),
+            None => format!("{:width$}  → {}", p.name, p.new),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `text` after `prefix`, with a space between unless there is no prefix.
+fn label(prefix: &str, text: &str) -> String {
+    if prefix.is_empty() {
+        text.to_string()
+    } else {
+        format!("{} {}", prefix, text)
+    }
+}
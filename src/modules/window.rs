@@ -0,0 +1,128 @@
+use gtk4::Label;
+use gtk4::pango::EllipsizeMode;
+use gtk4::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::{Context, Module, ModuleBuilder};
+use crate::services::Feed;
+use crate::services::compositor::Status;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// Label template with `{title}` and `{app_id}`.
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Templates replacing `format` for the windows of one app, by app id.
+    #[serde(default)]
+    pub rewrite: HashMap<String, String>,
+    /// Longest label in characters; longer ones are cut short with `…`.
+    pub max_length: Option<i32>,
+    /// Shown while no window is focused; the module hides when it is empty.
+    #[serde(default)]
+    pub empty: String,
+    #[serde(default = "default_tooltip")]
+    pub tooltip: bool,
+}
+
+fn default_format() -> String {
+    "{title}".to_string()
+}
+
+fn default_tooltip() -> bool {
+    true
+}
+
+pub struct Window {
+    label: Label,
+    format: String,
+    rewrite: HashMap<String, String>,
+    empty: String,
+    tooltip: bool,
+    feed: Feed<Status>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Window {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let label = Label::new(None);
+        label.add_css_class("window-title");
+        if let Some(max_length) = options.max_length {
+            label.set_max_width_chars(max_length);
+            label.set_ellipsize(EllipsizeMode::End);
+        }
+        label.set_visible(false);
+
+        Self {
+            label,
+            format: options.format,
+            rewrite: options.rewrite,
+            empty: options.empty,
+            tooltip: options.tooltip,
+            feed: ctx.services.compositor().0,
+            task: None,
+        }
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        if options.max_length.is_some_and(|n| n < 1) {
+            return Err("max-length must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Module for Window {
+    fn widget(&self) -> gtk4::Widget {
+        self.label.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        let label = self.label.clone();
+        let format = self.format.clone();
+        let rewrite = self.rewrite.clone();
+        let empty = self.empty.clone();
+        let tooltip = self.tooltip;
+
+        self.task = Some(self.feed.attach(move |status| {
+            let focused = match status {
+                Status::Connected(state) => &state.focused_window,
+                Status::Disconnected => &None,
+                // Not even `empty` is shown without a compositor to follow
+                Status::Unsupported => {
+                    label.set_visible(false);
+                    return;
+                }
+            };
+            let text = match focused {
+                Some(window) => {
+                    let template = rewrite.get(&window.app_id).unwrap_or(&format);
+                    template
+                        .replace("{app_id}", &window.app_id)
+                        .replace("{title}", &window.title)
+                }
+                None => empty.clone(),
+            };
+            if focused.is_some() {
+                label.remove_css_class("empty");
+            } else {
+                label.add_css_class("empty");
+            }
+            label.set_visible(!text.is_empty());
+            label.set_text(&text);
+            if tooltip {
+                let title = focused.as_ref().map(|w| w.title.as_str());
+                label.set_tooltip_text(title.filter(|t| !t.is_empty()));
+            }
+        }));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Button labels and the persistent workspaces shown alongside the live ones.
+use std::collections::HashMap;
+
+use crate::services::compositor::Workspace;
+
+/// Glyphs used when `format-icons` is not configured.
+pub fn default_icons() -> HashMap<String, String> {
+    [
+        ("1", ""),
+        ("2", ""),
+        ("3", ""),
+        ("4", ""),
+        ("5", ""),
+        ("6", ""),
+        ("7", ""),
+        ("default", ""),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+/// Expands `{name}`, `{index}`, `{roman}` and `{icon}` in `format` for the
+/// workspace at `position` (0-based) on the bar.
+pub fn label(
+    format: &str,
+    icons: &HashMap<String, String>,
+    ws: &Workspace,
+    position: usize,
+) -> String {
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        match &rest[1..end] {
+            "name" => out.push_str(&ws.name),
+            "index" => out.push_str(&(position + 1).to_string()),
+            "roman" => out.push_str(&roman(number(ws, position))),
+            "icon" => out.push_str(icon(icons, ws)),
+            _ => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// State icons win over per-name ones, e.g. a `focused` entry marks the
+/// focused workspace whatever its name.
+fn icon<'a>(icons: &'a HashMap<String, String>, ws: &'a Workspace) -> &'a str {
+    let states = [
+        ("urgent", ws.urgent),
+        ("focused", ws.focused),
+        ("visible", ws.visible),
+        ("empty", ws.windows.is_empty()),
+    ];
+    states
+        .into_iter()
+        .filter(|(_, active)| *active)
+        .find_map(|(state, _)| icons.get(state))
+        .or_else(|| icons.get(&ws.name))
+        .or_else(|| icons.get("default"))
+        .map_or(&ws.name, String::as_str)
+}
+
+/// The number a workspace name starts with, as in sway's `3:www`, else its
+/// position on the bar counting from 1.
+fn number(ws: &Workspace, position: usize) -> usize {
+    leading_number(&ws.name).unwrap_or(position + 1)
+}
+
+fn leading_number(name: &str) -> Option<usize> {
+    let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    name[..digits].parse().ok()
+}
+
+/// Roman numeral for `n`; numbers Roman numerals cannot write stay as they are.
+fn roman(mut n: usize) -> String {
+    if !(1..4000).contains(&n) {
+        return n.to_string();
+    }
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut out = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            out.push_str(numeral);
+            n -= value;
+        }
+    }
+    out
+}
+
+/// Adds a placeholder for every name in `persistent` the compositor does not
+/// report, ordered among the others by the number their names start with.
+pub fn with_persistent(mut workspaces: Vec<Workspace>, persistent: &[String]) -> Vec<Workspace> {
+    let missing: Vec<Workspace> = persistent
+        .iter()
+        .filter(|name| !workspaces.iter().any(|ws| &ws.name == *name))
+        .map(|name| Workspace {
+            id: None,
+            name: name.clone(),
+            focused: false,
+            visible: false,
+            urgent: false,
+            output: None,
+            windows: Vec::new(),
+        })
+        .collect();
+    if missing.is_empty() {
+        return workspaces;
+    }
+    workspaces.extend(missing);
+    // Stable, so unnumbered workspaces keep the compositor's order
+    workspaces.sort_by_key(|ws| leading_number(&ws.name).unwrap_or(usize::MAX));
+    workspaces
+}
@@ -0,0 +1,72 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Shown for windows whose app has no icon we can find.
+const FALLBACK: &str = "application-x-executable";
+
+/// Resolves window app ids to icons, remembering each answer.
+pub struct AppIcons {
+    /// Icon names or paths configured per app id, taking precedence over lookup.
+    rewrite: HashMap<String, String>,
+    cache: RefCell<HashMap<String, gio::Icon>>,
+}
+
+impl AppIcons {
+    pub fn new(rewrite: HashMap<String, String>) -> Self {
+        Self {
+            rewrite,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn lookup(&self, app_id: &str) -> gio::Icon {
+        self.cache
+            .borrow_mut()
+            .entry(app_id.to_string())
+            .or_insert_with(|| self.resolve(app_id))
+            .clone()
+    }
+
+    /// The configured icon, else a theme icon named after the app id, else
+    /// the icon of the app's desktop file.
+    fn resolve(&self, app_id: &str) -> gio::Icon {
+        if let Some(icon) = self.rewrite.get(app_id) {
+            match gio::Icon::for_string(icon) {
+                Ok(icon) => return icon,
+                Err(e) => eprintln!("vibebar-p4: icon `{}` for `{}`: {}", icon, app_id, e),
+            }
+        }
+        if app_id.is_empty() {
+            return gio::ThemedIcon::new(FALLBACK).upcast();
+        }
+
+        let lowercase = app_id.to_lowercase();
+        if let Some(display) = gtk4::gdk::Display::default() {
+            let theme = gtk4::IconTheme::for_display(&display);
+            for name in [app_id, lowercase.as_str()] {
+                if theme.has_icon(name) {
+                    return gio::ThemedIcon::new(name).upcast();
+                }
+            }
+        }
+
+        desktop_icon(app_id)
+            .or_else(|| desktop_icon(&lowercase))
+            .or_else(|| search_icon(app_id))
+            .unwrap_or_else(|| gio::ThemedIcon::new(FALLBACK).upcast())
+    }
+}
+
+fn desktop_icon(app_id: &str) -> Option<gio::Icon> {
+    gio::DesktopAppInfo::new(&format!("{}.desktop", app_id))?.icon()
+}
+
+/// The best desktop file search hit, which also matches `StartupWMClass`
+/// and names that differ from the app id, e.g. `Alacritty` for `alacritty`.
+fn search_icon(app_id: &str) -> Option<gio::Icon> {
+    let results = gio::DesktopAppInfo::search(app_id);
+    let best = results.first()?.first()?;
+    gio::DesktopAppInfo::new(best)?.icon()
+}
@@ -0,0 +1,428 @@
+use gtk4::prelude::*;
+use gtk4::{
+    Box, Button, EventControllerScroll, EventControllerScrollFlags, GestureClick, Image, Label,
+    Orientation,
+};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{Context, Module, ModuleBuilder};
+use crate::services::Feed;
+use crate::services::compositor::{Action, Command, State, Status, Workspace};
+use icons::AppIcons;
+
+mod format;
+mod icons;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// Button label with `{name}`, `{index}`, `{roman}` and `{icon}`.
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Icons for `{icon}` by workspace name, or by state (`urgent`, `focused`,
+    /// `visible`, `empty`), with a `default` for the rest.
+    #[serde(default = "format::default_icons")]
+    pub format_icons: HashMap<String, String>,
+    /// Workspace names shown even while the compositor has not created them.
+    #[serde(default)]
+    pub persistent_workspaces: Vec<String>,
+    /// Show an icon for every app with a window on the workspace.
+    #[serde(default = "default_show_windows")]
+    pub show_windows: bool,
+    /// Icon name or path per app id, for apps whose icon is not found or
+    /// should be replaced, e.g. `footclient = "foot"`.
+    #[serde(default)]
+    pub window_icons: HashMap<String, String>,
+    #[serde(default = "default_icon_size")]
+    pub icon_size: i32,
+    #[serde(default)]
+    pub on_click: Action,
+    pub on_click_middle: Option<Action>,
+    pub on_click_right: Option<Action>,
+    /// Scrolling over the module moves to the next or previous workspace.
+    #[serde(default = "default_scroll")]
+    pub scroll: bool,
+    /// Scroll from the last workspace to the first and back.
+    #[serde(default)]
+    pub scroll_wrap: bool,
+    /// Scroll through the workspaces of every output, not just this bar's.
+    #[serde(default)]
+    pub scroll_all_outputs: bool,
+}
+
+fn default_format() -> String {
+    "{icon}".to_string()
+}
+
+fn default_show_windows() -> bool {
+    true
+}
+
+fn default_icon_size() -> i32 {
+    16
+}
+
+fn default_scroll() -> bool {
+    true
+}
+
+/// The latest workspaces, kept for scrolling.
+#[derive(Default)]
+struct Listing {
+    /// On every output, in the compositor's order.
+    all: Vec<Workspace>,
+    /// Those with a button on this bar, persistent ones included.
+    shown: Vec<Workspace>,
+}
+
+pub struct Workspaces {
+    workspaces_box: Box,
+    /// Stands in for the buttons while the compositor is unreachable.
+    disconnected: Label,
+    view: Rc<View>,
+    listing: Rc<RefCell<Listing>>,
+    scroll: Option<EventControllerScroll>,
+    feed: Feed<Status>,
+    commands: UnboundedSender<Command>,
+    task: Option<glib::JoinHandle<()>>,
+}
+
+impl ModuleBuilder for Workspaces {
+    type Options = Options;
+
+    fn new(options: Options, ctx: &Context) -> Self {
+        let workspaces_box = Box::new(ctx.orientation, 0);
+        workspaces_box.add_css_class("workspaces-box");
+        let disconnected = Label::new(Some("disconnected"));
+        disconnected.add_css_class("workspaces-disconnected");
+        disconnected.set_visible(false);
+        workspaces_box.append(&disconnected);
+        let (feed, commands) = ctx.services.compositor();
+
+        Self {
+            workspaces_box,
+            disconnected,
+            view: Rc::new(View {
+                monitor: ctx.monitor.clone(),
+                format: options.format,
+                format_icons: options.format_icons,
+                persistent: options.persistent_workspaces,
+                orientation: ctx.orientation,
+                icons: options
+                    .show_windows
+                    .then(|| AppIcons::new(options.window_icons)),
+                icon_size: options.icon_size,
+                clicks: [
+                    (1, Some(options.on_click)),
+                    (2, options.on_click_middle),
+                    (3, options.on_click_right),
+                ],
+                scroll: options.scroll,
+                scroll_wrap: options.scroll_wrap,
+                scroll_all_outputs: options.scroll_all_outputs,
+            }),
+            listing: Rc::new(RefCell::new(Listing::default())),
+            scroll: None,
+            feed,
+            commands,
+            task: None,
+        }
+    }
+
+    fn validate(options: &Options) -> Result<(), String> {
+        if options.icon_size < 1 {
+            return Err("icon-size must be at least 1 pixel".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Module for Workspaces {
+    fn widget(&self) -> gtk4::Widget {
+        self.workspaces_box.clone().upcast()
+    }
+
+    fn start(&mut self) {
+        if self.view.scroll {
+            // Discrete, so a touchpad swipe moves one workspace rather than many
+            let scroll = EventControllerScroll::new(
+                EventControllerScrollFlags::VERTICAL | EventControllerScrollFlags::DISCRETE,
+            );
+            let view = self.view.clone();
+            let listing = self.listing.clone();
+            let commands = self.commands.clone();
+            scroll.connect_scroll(move |_, _, dy| {
+                let step = if dy < 0.0 { -1 } else { 1 };
+                if let Some(ws) = scroll_target(&listing.borrow(), &view, step) {
+                    let _ = commands.send((Action::Focus, ws));
+                }
+                glib::Propagation::Stop
+            });
+            self.workspaces_box.add_controller(scroll.clone());
+            self.scroll = Some(scroll);
+        }
+
+        let wbox = self.workspaces_box.clone();
+        let view = self.view.clone();
+        let listing = self.listing.clone();
+        let commands = self.commands.clone();
+        let entries: RefCell<HashMap<Key, Entry>> = RefCell::new(HashMap::new());
+        let disconnected = self.disconnected.clone();
+
+        self.task = Some(self.feed.attach(move |status| {
+            let mut entries = entries.borrow_mut();
+            let state = match status {
+                Status::Connected(state) => state,
+                Status::Unsupported => {
+                    wbox.set_visible(false);
+                    return;
+                }
+                Status::Disconnected => {
+                    // Drop the buttons rather than leave stale ones clickable
+                    for (_, entry) in entries.drain() {
+                        wbox.remove(&entry.button);
+                    }
+                    *listing.borrow_mut() = Listing::default();
+                    wbox.add_css_class("disconnected");
+                    disconnected.set_visible(true);
+                    return;
+                }
+            };
+            wbox.remove_css_class("disconnected");
+            disconnected.set_visible(false);
+
+            let shown = format::with_persistent(on_output(state, &view), &view.persistent);
+            update_workspaces(&wbox, &view, &mut entries, &shown, &commands);
+            *listing.borrow_mut() = Listing {
+                all: state.workspaces.clone(),
+                shown,
+            };
+        }));
+    }
+
+    fn stop(&mut self) {
+        if let Some(scroll) = self.scroll.take() {
+            self.workspaces_box.remove_controller(&scroll);
+        }
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+/// How buttons are drawn, fixed for the lifetime of the module.
+struct View {
+    /// Output this bar is on; only its workspaces get buttons.
+    monitor: Option<String>,
+    format: String,
+    format_icons: HashMap<String, String>,
+    persistent: Vec<String>,
+    orientation: Orientation,
+    /// Set when window icons are shown.
+    icons: Option<AppIcons>,
+    icon_size: i32,
+    /// Action per mouse button, if any.
+    clicks: [(u32, Option<Action>); 3],
+    scroll: bool,
+    scroll_wrap: bool,
+    scroll_all_outputs: bool,
+}
+
+/// The workspaces with a button on this bar. A bar on an output the
+/// compositor does not know, e.g. when connector names differ, shows all.
+fn on_output(state: &State, view: &View) -> Vec<Workspace> {
+    let monitor = view.monitor.as_ref().filter(|m| state.outputs.contains(m));
+    state
+        .workspaces
+        .iter()
+        .filter(|ws| monitor.is_none_or(|m| ws.output.as_ref() == Some(m)))
+        .cloned()
+        .collect()
+}
+
+/// The workspace `step` places from the current one, or `None` past either
+/// end without wrapping.
+fn scroll_target(listing: &Listing, view: &View, step: isize) -> Option<Workspace> {
+    let list = if view.scroll_all_outputs {
+        &listing.all
+    } else {
+        &listing.shown
+    };
+    // On another output than the focused one, scroll from the one it shows
+    let current = list
+        .iter()
+        .position(|ws| ws.focused)
+        .or_else(|| list.iter().position(|ws| ws.visible))?;
+    let next = current as isize + step;
+    let len = list.len() as isize;
+    let next = if view.scroll_wrap {
+        next.rem_euclid(len)
+    } else if (0..len).contains(&next) {
+        next
+    } else {
+        return None;
+    };
+    list.get(next as usize).cloned()
+}
+
+/// Identifies a workspace's button across updates: niri ids survive renames
+/// and reordering, sway workspaces only have their name.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Id(u64),
+    Name(String),
+}
+
+impl Workspace {
+    fn key(&self) -> Key {
+        match self.id {
+            Some(id) => Key::Id(id),
+            None => Key::Name(self.name.clone()),
+        }
+    }
+}
+
+/// The button of one workspace, updated in place while it exists.
+struct Entry {
+    button: Button,
+    label: Label,
+    /// Window icons, after the label.
+    icons: Box,
+    /// App ids the icons were drawn for.
+    apps: Vec<String>,
+    /// What a click focuses, refreshed on every update.
+    workspace: Rc<RefCell<Workspace>>,
+}
+
+impl Entry {
+    fn new(view: &View, ws: &Workspace, tx: &UnboundedSender<Command>) -> Self {
+        let content = Box::new(view.orientation, 4);
+        let label = Label::new(None);
+        let icons = Box::new(view.orientation, 4);
+        icons.set_visible(false);
+        content.append(&label);
+        content.append(&icons);
+
+        let button = Button::builder().child(&content).build();
+        button.add_css_class("workspace-btn");
+        button.add_css_class("btn");
+
+        let workspace = Rc::new(RefCell::new(ws.clone()));
+        for (mouse_button, action) in view.clicks {
+            let Some(action) = action else {
+                continue;
+            };
+            let tx_clone = tx.clone();
+            let ws_clone = workspace.clone();
+            let send = move || {
+                let _ = tx_clone.send((action, ws_clone.borrow().clone()));
+            };
+            if mouse_button == 1 {
+                // Also reached from the keyboard
+                button.connect_clicked(move |_| send());
+            } else {
+                let gesture = GestureClick::new();
+                gesture.set_button(mouse_button);
+                gesture.connect_pressed(move |_, _, _, _| send());
+                button.add_controller(gesture);
+            }
+        }
+
+        Self {
+            button,
+            label,
+            icons,
+            apps: Vec::new(),
+            workspace,
+        }
+    }
+
+    fn update(&mut self, view: &View, ws: Workspace, position: usize) {
+        let text = format::label(&view.format, &view.format_icons, &ws, position);
+        if self.label.text() != text {
+            self.label.set_text(&text);
+        }
+
+        for (class, active) in [
+            ("focused", ws.focused),
+            ("visible", ws.visible),
+            ("urgent", ws.urgent),
+            ("empty", ws.windows.is_empty()),
+        ] {
+            if active {
+                self.button.add_css_class(class);
+            } else {
+                self.button.remove_css_class(class);
+            }
+        }
+
+        if let Some(icons) = &view.icons {
+            // One icon per app, however many windows it has open
+            let mut apps: Vec<String> = Vec::new();
+            for app_id in &ws.windows {
+                if !apps.contains(app_id) {
+                    apps.push(app_id.clone());
+                }
+            }
+            if apps != self.apps {
+                while let Some(child) = self.icons.first_child() {
+                    self.icons.remove(&child);
+                }
+                for app_id in &apps {
+                    let image = Image::from_gicon(&icons.lookup(app_id));
+                    image.set_pixel_size(view.icon_size);
+                    image.add_css_class("workspace-window");
+                    image.set_tooltip_text(Some(app_id));
+                    self.icons.append(&image);
+                }
+                self.icons.set_visible(!apps.is_empty());
+                self.apps = apps;
+            }
+        }
+
+        *self.workspace.borrow_mut() = ws;
+    }
+}
+
+/// Brings the buttons in `container` in line with `ws_data`, only creating
+/// and removing those of workspaces that appeared or went away.
+fn update_workspaces(
+    container: &Box,
+    view: &View,
+    entries: &mut HashMap<Key, Entry>,
+    ws_data: &[Workspace],
+    tx: &UnboundedSender<Command>,
+) {
+    let keys: HashSet<Key> = ws_data.iter().map(Workspace::key).collect();
+    entries.retain(|key, entry| {
+        if keys.contains(key) {
+            true
+        } else {
+            container.remove(&entry.button);
+            false
+        }
+    });
+
+    let mut previous: Option<Button> = None;
+    for (position, ws) in ws_data.iter().enumerate() {
+        let entry = entries.entry(ws.key()).or_insert_with(|| {
+            let entry = Entry::new(view, ws, tx);
+            container.append(&entry.button);
+            entry
+        });
+        entry.update(view, ws.clone(), position);
+
+        // Keep the buttons in workspace order, moving only those out of place
+        let expected = previous
+            .as_ref()
+            .map(|b| b.clone().upcast::<gtk4::Widget>());
+        if entry.button.prev_sibling() != expected {
+            container.reorder_child_after(&entry.button, previous.as_ref());
+        }
+        previous = Some(entry.button.clone());
+    }
+}
@@ -0,0 +1,60 @@
+//! Async counterpart of `niri_ipc::socket::Socket`, so niri can be talked to
+//! from tasks on the shared runtime instead of dedicated blocking threads.
+use niri_ipc::socket::SOCKET_PATH_ENV;
+use niri_ipc::{Event, Reply, Request, Response};
+use std::io;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+pub struct Socket {
+    stream: BufReader<UnixStream>,
+    buf: String,
+}
+
+impl Socket {
+    /// Connects to the socket named by `$NIRI_SOCKET`.
+    pub async fn connect() -> io::Result<Self> {
+        let path = std::env::var_os(SOCKET_PATH_ENV).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not set", SOCKET_PATH_ENV),
+            )
+        })?;
+        let stream = UnixStream::connect(path).await?;
+        Ok(Self {
+            stream: BufReader::new(stream),
+            buf: String::new(),
+        })
+    }
+
+    /// Connects and switches the socket to streaming events, read with
+    /// [`Socket::read_event`].
+    pub async fn event_stream() -> io::Result<Self> {
+        let mut socket = Self::connect().await?;
+        match socket.send(Request::EventStream).await? {
+            Ok(Response::Handled) => Ok(socket),
+            Ok(_) => Err(io::Error::other("unexpected reply to EventStream")),
+            Err(message) => Err(io::Error::other(message)),
+        }
+    }
+
+    pub async fn send(&mut self, request: Request) -> io::Result<Reply> {
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        self.stream.get_mut().write_all(line.as_bytes()).await?;
+        self.read_line().await
+    }
+
+    /// Reads the next event; only valid after a successful [`Request::EventStream`].
+    pub async fn read_event(&mut self) -> io::Result<Event> {
+        self.read_line().await
+    }
+
+    async fn read_line<T: serde::de::DeserializeOwned>(&mut self) -> io::Result<T> {
+        self.buf.clear();
+        if self.stream.read_line(&mut self.buf).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&self.buf)?)
+    }
+}
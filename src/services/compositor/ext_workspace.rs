@@ -0,0 +1,417 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::OwnedFd;
+use std::sync::{Arc, Mutex};
+use tokio::io::unix::AsyncFd;
+use wayland_client::backend::{ObjectId, WaylandError};
+use wayland_client::globals::{BindError, GlobalListContents, registry_queue_init};
+use wayland_client::protocol::{wl_output, wl_registry};
+use wayland_client::{Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum};
+use wayland_protocols::ext::workspace::v1::client::{
+    ext_workspace_group_handle_v1 as group, ext_workspace_handle_v1 as handle,
+    ext_workspace_manager_v1 as manager,
+};
+
+use super::{Action, Commander, CompositorBackend, State, Workspace};
+
+/// `wl_output` sends its connector name from version 4 on.
+const OUTPUT_VERSION: u32 = 4;
+
+struct Output {
+    proxy: wl_output::WlOutput,
+    /// Registry name of its global, to notice it going away.
+    global: u32,
+    /// Connector name; empty before `wl_output` version 4.
+    name: String,
+}
+
+#[derive(Default)]
+struct Group {
+    outputs: Vec<ObjectId>,
+    workspaces: Vec<ObjectId>,
+}
+
+struct Handle {
+    proxy: handle::ExtWorkspaceHandleV1,
+    name: String,
+    coordinates: Vec<u8>,
+    state: handle::State,
+    /// Order of announcement, for workspaces without coordinates.
+    created: usize,
+}
+
+/// What the compositor announced so far, updated as events are dispatched.
+#[derive(Default)]
+struct Data {
+    outputs: HashMap<ObjectId, Output>,
+    groups: HashMap<ObjectId, Group>,
+    workspaces: HashMap<ObjectId, Handle>,
+    announced: usize,
+    /// Set by the manager's first `done`; the state is incomplete before.
+    ready: bool,
+    /// A complete change arrived since the last state went out.
+    changed: bool,
+    finished: bool,
+}
+
+/// Any compositor implementing the `ext-workspace-v1` Wayland protocol, e.g.
+/// labwc, river or KWin, over a Wayland connection of the bar's own.
+///
+/// The protocol knows nothing of windows or keyboard focus: workspaces have
+/// no app icons, and every workspace shown on an output counts as focused.
+pub struct ExtWorkspace {
+    connection: Connection,
+    queue: EventQueue<Data>,
+    /// Shared with the commander, which looks up the handles to activate.
+    data: Arc<Mutex<Data>>,
+    manager: manager::ExtWorkspaceManagerV1,
+    /// The connection's socket, to wait for events on the runtime.
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl ExtWorkspace {
+    pub async fn connect() -> io::Result<Self> {
+        // Without a display to connect to, there is nothing to wait for; a
+        // socket that refuses connections may be a compositor restarting
+        if std::env::var_os("WAYLAND_DISPLAY").is_none()
+            && std::env::var_os("WAYLAND_SOCKET").is_none()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "no Wayland display is set",
+            ));
+        }
+        let connection = Connection::connect_to_env().map_err(io::Error::other)?;
+        // A single roundtrip that the compositor answers right away
+        let (globals, queue) = tokio::task::block_in_place(|| registry_queue_init(&connection))
+            .map_err(io::Error::other)?;
+        let qh = queue.handle();
+
+        let manager = globals.bind(&qh, 1..=1, ()).map_err(|e| match e {
+            BindError::NotPresent => io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the compositor does not offer ext-workspace-v1",
+            ),
+            e => io::Error::other(e),
+        })?;
+        let mut data = Data::default();
+        globals.contents().with_list(|list| {
+            for global in list.iter().filter(|g| g.interface == "wl_output") {
+                data.bind_output(globals.registry(), &qh, global.name, global.version);
+            }
+        });
+        connection.flush().map_err(io::Error::other)?;
+
+        let fd = connection.backend().poll_fd().try_clone_to_owned()?;
+        // SAFETY: the duplicate is owned here and stays open, unchanged, for
+        // as long as the `AsyncFd` holds it
+        let fd = unsafe { AsyncFd::register(fd)? };
+        Ok(Self {
+            fd,
+            connection,
+            queue,
+            data: Arc::new(Mutex::new(data)),
+            manager,
+        })
+    }
+}
+
+#[async_trait]
+impl CompositorBackend for ExtWorkspace {
+    async fn next_state(&mut self) -> io::Result<State> {
+        loop {
+            {
+                let mut data = self.data.lock().unwrap();
+                self.queue
+                    .dispatch_pending(&mut data)
+                    .map_err(io::Error::other)?;
+                if data.finished {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the compositor stopped sending workspaces",
+                    ));
+                }
+                if std::mem::take(&mut data.changed) {
+                    return Ok(data.state());
+                }
+            }
+            // Destroy requests made while dispatching
+            self.queue.flush().map_err(io::Error::other)?;
+
+            let mut ready = self.fd.readable().await?;
+            // `None` when events are already queued and need dispatching first
+            if let Some(guard) = self.queue.prepare_read() {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                        ready.clear_ready();
+                    }
+                    Err(e) => return Err(io::Error::other(e)),
+                }
+            }
+        }
+    }
+
+    async fn commander(&mut self) -> io::Result<Box<dyn Commander>> {
+        // Requests go out on the same connection, as objects are per client
+        Ok(Box::new(ExtWorkspaceCommander {
+            connection: self.connection.clone(),
+            data: self.data.clone(),
+            manager: self.manager.clone(),
+        }))
+    }
+}
+
+struct ExtWorkspaceCommander {
+    connection: Connection,
+    data: Arc<Mutex<Data>>,
+    manager: manager::ExtWorkspaceManagerV1,
+}
+
+#[async_trait]
+impl Commander for ExtWorkspaceCommander {
+    async fn run(&mut self, action: Action, workspace: &Workspace) -> io::Result<()> {
+        if action != Action::Focus {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "ext-workspace-v1 cannot move windows",
+            ));
+        }
+        {
+            // Persistent workspaces that do not exist yet have no handle
+            let data = self.data.lock().unwrap();
+            let handle = data
+                .workspaces
+                .values()
+                .find(|h| Some(u64::from(h.proxy.id().protocol_id())) == workspace.id)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such workspace"))?;
+            handle.proxy.activate();
+        }
+        // Changes only take effect once committed
+        self.manager.commit();
+        self.connection.flush().map_err(io::Error::other)
+    }
+}
+
+impl Data {
+    fn bind_output(
+        &mut self,
+        registry: &wl_registry::WlRegistry,
+        qh: &QueueHandle<Self>,
+        global: u32,
+        version: u32,
+    ) {
+        let proxy: wl_output::WlOutput = registry.bind(global, version.min(OUTPUT_VERSION), qh, ());
+        let output = Output {
+            proxy,
+            global,
+            name: String::new(),
+        };
+        self.outputs.insert(output.proxy.id(), output);
+    }
+
+    fn state(&self) -> State {
+        let mut handles: Vec<(&ObjectId, &Handle)> = self
+            .workspaces
+            .iter()
+            .filter(|(_, h)| !h.state.contains(handle::State::Hidden))
+            .collect();
+        handles.sort_by_key(|(_, h)| (coordinates(&h.coordinates), h.created));
+
+        let workspaces = handles
+            .into_iter()
+            .map(|(id, h)| {
+                let output = self
+                    .groups
+                    .values()
+                    .find(|group| group.workspaces.contains(id))
+                    .and_then(|group| group.outputs.first())
+                    .and_then(|output| self.outputs.get(output))
+                    .map(|output| output.name.clone())
+                    .filter(|name| !name.is_empty());
+                let active = h.state.contains(handle::State::Active);
+                Workspace {
+                    id: Some(u64::from(id.protocol_id())),
+                    name: h.name.clone(),
+                    focused: active,
+                    visible: active,
+                    urgent: h.state.contains(handle::State::Urgent),
+                    windows: Vec::new(),
+                    output,
+                }
+            })
+            .collect();
+
+        let mut outputs: Vec<String> = self
+            .outputs
+            .values()
+            .map(|output| output.name.clone())
+            .filter(|name| !name.is_empty())
+            .collect();
+        outputs.sort();
+        State {
+            workspaces,
+            outputs,
+            focused_window: None,
+            layout: None,
+        }
+    }
+}
+
+/// The `coordinates` array as the 32-bit numbers it holds.
+fn coordinates(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Data {
+    fn event(
+        data: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == "wl_output" => data.bind_output(registry, qh, name, version),
+            wl_registry::Event::GlobalRemove { name } => {
+                let removed = data.outputs.iter().find(|(_, o)| o.global == name);
+                if let Some(id) = removed.map(|(id, _)| id.clone())
+                    && let Some(output) = data.outputs.remove(&id)
+                {
+                    if output.proxy.version() >= 3 {
+                        output.proxy.release();
+                    }
+                    data.changed |= data.ready;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, ()> for Data {
+    fn event(
+        data: &mut Self,
+        proxy: &wl_output::WlOutput,
+        event: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_output::Event::Name { name } => {
+                if let Some(output) = data.outputs.get_mut(&proxy.id()) {
+                    output.name = name;
+                }
+            }
+            wl_output::Event::Done => data.changed |= data.ready,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<manager::ExtWorkspaceManagerV1, ()> for Data {
+    fn event(
+        data: &mut Self,
+        _: &manager::ExtWorkspaceManagerV1,
+        event: manager::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            manager::Event::WorkspaceGroup { workspace_group } => {
+                data.groups.insert(workspace_group.id(), Group::default());
+            }
+            manager::Event::Workspace { workspace } => {
+                data.announced += 1;
+                let handle = Handle {
+                    proxy: workspace,
+                    name: String::new(),
+                    coordinates: Vec::new(),
+                    state: handle::State::empty(),
+                    created: data.announced,
+                };
+                data.workspaces.insert(handle.proxy.id(), handle);
+            }
+            // Changes come in batches, each ending with `done`
+            manager::Event::Done => {
+                data.ready = true;
+                data.changed = true;
+            }
+            manager::Event::Finished => data.finished = true,
+            _ => {}
+        }
+    }
+
+    wayland_client::event_created_child!(Data, manager::ExtWorkspaceManagerV1, [
+        manager::EVT_WORKSPACE_GROUP_OPCODE => (group::ExtWorkspaceGroupHandleV1, ()),
+        manager::EVT_WORKSPACE_OPCODE => (handle::ExtWorkspaceHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<group::ExtWorkspaceGroupHandleV1, ()> for Data {
+    fn event(
+        data: &mut Self,
+        proxy: &group::ExtWorkspaceGroupHandleV1,
+        event: group::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let group::Event::Removed = event {
+            data.groups.remove(&proxy.id());
+            proxy.destroy();
+            return;
+        }
+        let Some(group) = data.groups.get_mut(&proxy.id()) else {
+            return;
+        };
+        match event {
+            group::Event::OutputEnter { output } => group.outputs.push(output.id()),
+            group::Event::OutputLeave { output } => group.outputs.retain(|o| *o != output.id()),
+            group::Event::WorkspaceEnter { workspace } => group.workspaces.push(workspace.id()),
+            group::Event::WorkspaceLeave { workspace } => {
+                group.workspaces.retain(|w| *w != workspace.id())
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<handle::ExtWorkspaceHandleV1, ()> for Data {
+    fn event(
+        data: &mut Self,
+        proxy: &handle::ExtWorkspaceHandleV1,
+        event: handle::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let handle::Event::Removed = event {
+            data.workspaces.remove(&proxy.id());
+            proxy.destroy();
+            return;
+        }
+        let Some(handle) = data.workspaces.get_mut(&proxy.id()) else {
+            return;
+        };
+        match event {
+            handle::Event::Name { name } => handle.name = name,
+            handle::Event::Coordinates { coordinates } => handle.coordinates = coordinates,
+            handle::Event::State {
+                state: WEnum::Value(state),
+            } => handle.state = state,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::{Action, Commander, CompositorBackend, FocusedWindow, State, Workspace};
+use crate::hyprland;
+
+/// Events after which the state is queried again. Hyprland sends most of them
+/// twice, in the original and the `v2` format; only one of each is listed.
+const RELEVANT_EVENTS: [&str; 13] = [
+    "workspacev2",
+    "focusedmonv2",
+    "createworkspacev2",
+    "destroyworkspacev2",
+    "moveworkspacev2",
+    "renameworkspace",
+    "openwindow",
+    "closewindow",
+    "movewindowv2",
+    "activewindowv2",
+    "windowtitlev2",
+    "monitoraddedv2",
+    "monitorremovedv2",
+];
+
+/// Relevant events this close together are merged into one query, e.g. the
+/// handful sent when switching workspaces.
+const SETTLE: Duration = Duration::from_millis(50);
+/// A steady stream of them, e.g. a title changing every few milliseconds, is
+/// still queried this often.
+const MAX_DELAY: Duration = Duration::from_millis(250);
+
+#[derive(Deserialize)]
+struct HyprWorkspace {
+    id: i64,
+    name: String,
+    monitor: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Monitor {
+    name: String,
+    focused: bool,
+    active_workspace: WorkspaceRef,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceRef {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct Client {
+    address: String,
+    class: String,
+    workspace: WorkspaceRef,
+    at: (i32, i32),
+}
+
+/// `j/activewindow` replies `{}` while no window is focused.
+#[derive(Deserialize)]
+struct ActiveWindow {
+    #[serde(default)]
+    address: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    title: String,
+}
+
+pub struct Hyprland {
+    events: hyprland::Events,
+    /// Addresses of windows that asked for attention.
+    urgent: HashSet<String>,
+    /// Set once the initial state went out; later calls wait for an event.
+    started: bool,
+}
+
+impl Hyprland {
+    pub async fn connect() -> io::Result<Self> {
+        Ok(Self {
+            events: hyprland::Events::connect().await?,
+            urgent: HashSet::new(),
+            started: false,
+        })
+    }
+
+    /// Waits for an event that changes the state. Safe to cancel, as reading
+    /// events is.
+    async fn changed(&mut self) -> io::Result<()> {
+        loop {
+            let (name, data) = self.events.next().await?;
+            if name == "urgent" {
+                self.urgent.insert(format!("0x{}", data));
+                return Ok(());
+            }
+            if RELEVANT_EVENTS.contains(&name.as_str()) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CompositorBackend for Hyprland {
+    async fn next_state(&mut self) -> io::Result<State> {
+        if self.started {
+            self.changed().await?;
+            let deadline = Instant::now() + MAX_DELAY;
+            loop {
+                let settled = (Instant::now() + SETTLE).min(deadline);
+                match tokio::time::timeout_at(settled, self.changed()).await {
+                    Ok(changed) => changed?,
+                    Err(_) => break,
+                }
+            }
+        }
+        self.started = true;
+        query(&mut self.urgent).await
+    }
+
+    async fn commander(&mut self) -> io::Result<Box<dyn Commander>> {
+        // Every request goes over a connection of its own
+        Ok(Box::new(HyprlandCommander))
+    }
+}
+
+struct HyprlandCommander;
+
+#[async_trait]
+impl Commander for HyprlandCommander {
+    async fn run(&mut self, action: Action, workspace: &Workspace) -> io::Result<()> {
+        let dispatcher = match action {
+            Action::Focus => "workspace",
+            Action::MoveWindow => "movetoworkspacesilent",
+            Action::MoveWindowAndFollow => "movetoworkspace",
+        };
+        hyprland::dispatch(dispatcher, &target(workspace)).await
+    }
+}
+
+/// Numbered workspaces go by number, others by `name:`.
+fn target(ws: &Workspace) -> String {
+    if ws.name.parse::<i64>().is_ok() {
+        ws.name.clone()
+    } else {
+        format!("name:{}", ws.name)
+    }
+}
+
+async fn query(urgent: &mut HashSet<String>) -> io::Result<State> {
+    let mut workspaces: Vec<HyprWorkspace> = hyprland::query("workspaces").await?;
+    let monitors: Vec<Monitor> = hyprland::query("monitors").await?;
+    let mut clients: Vec<Client> = hyprland::query("clients").await?;
+    let active: ActiveWindow = hyprland::query("activewindow").await?;
+
+    let visible: Vec<i64> = monitors.iter().map(|m| m.active_workspace.id).collect();
+    let focused = monitors
+        .iter()
+        .find(|m| m.focused)
+        .map(|m| m.active_workspace.id);
+    // Like sway, a window stops being urgent once its workspace is focused
+    urgent.retain(|address| {
+        clients
+            .iter()
+            .find(|c| &c.address == address)
+            .is_some_and(|c| Some(c.workspace.id) != focused)
+    });
+
+    // Scratchpads are not workspaces to switch to
+    workspaces.retain(|w| !w.name.starts_with("special:"));
+    workspaces.sort_by_key(|w| w.id);
+    clients.sort_by_key(|c| c.at);
+
+    Ok(State {
+        workspaces: workspaces
+            .into_iter()
+            .map(|w| {
+                let on_it = || clients.iter().filter(move |c| c.workspace.id == w.id);
+                Workspace {
+                    id: None,
+                    focused: focused == Some(w.id),
+                    visible: visible.contains(&w.id),
+                    urgent: on_it().any(|c| urgent.contains(&c.address)),
+                    windows: on_it().map(|c| c.class.clone()).collect(),
+                    output: Some(w.monitor),
+                    name: w.name,
+                }
+            })
+            .collect(),
+        outputs: monitors.into_iter().map(|m| m.name).collect(),
+        focused_window: (!active.address.is_empty()).then_some(FocusedWindow {
+            title: active.title,
+            app_id: active.class,
+        }),
+        layout: None,
+    })
+}
@@ -0,0 +1,216 @@
+//! Workspaces, outputs and windows of the running compositor, over one
+//! connection shared by every bar.
+//!
+//! Each supported compositor implements [`CompositorBackend`], with
+//! `ext-workspace-v1` as the generic fallback; [`run`] picks one from the
+//! environment and reconnects with backoff when the connection
+//! drops, e.g. while the compositor reloads.
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::io;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::watch;
+
+mod ext_workspace;
+mod hyprland;
+mod niri;
+mod sway;
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// A workspace as reported by the compositor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workspace {
+    /// Niri's workspace id, or the handle's object id with `ext-workspace-v1`;
+    /// unset on sway and Hyprland, which go by name, and for persistent
+    /// workspaces that do not exist yet.
+    pub id: Option<u64>,
+    /// The workspace's name, or on niri its index when unnamed.
+    pub name: String,
+    /// Holds keyboard focus; only one workspace across all outputs does.
+    pub focused: bool,
+    /// Shown on its output, focused or not.
+    pub visible: bool,
+    pub urgent: bool,
+    /// App ids of its windows in layout order; empty for windows without one.
+    pub windows: Vec<String>,
+    /// Connector name of the output it is on.
+    pub output: Option<String>,
+}
+
+/// The window holding keyboard focus.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FocusedWindow {
+    pub title: String,
+    pub app_id: String,
+}
+
+/// What the bar shows about the compositor.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct State {
+    /// On every output, in the compositor's order.
+    pub workspaces: Vec<Workspace>,
+    /// Connector names of the outputs.
+    pub outputs: Vec<String>,
+    pub focused_window: Option<FocusedWindow>,
+    /// Only known on niri.
+    pub layout: Option<Layout>,
+}
+
+/// Niri's scrolling layout and keyboard layouts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    /// Column of the focused workspace's active window, from 1; unset when it
+    /// floats or the workspace is empty.
+    pub column: Option<usize>,
+    /// Columns on the focused workspace.
+    pub columns: usize,
+    /// XKB names of the configured keyboard layouts.
+    pub keyboard_layouts: Vec<String>,
+    /// Index of the active one in `keyboard_layouts`.
+    pub current_layout: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    Connected(State),
+    /// The connection is lost or could not be made; retried in the background.
+    Disconnected,
+    /// No supported compositor is running; not retried.
+    Unsupported,
+}
+
+/// What clicking a workspace button does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    #[default]
+    Focus,
+    /// Move the focused window to the workspace.
+    MoveWindow,
+    /// Move the focused window to the workspace and follow it there.
+    MoveWindowAndFollow,
+}
+
+/// Sent to the backend to have the compositor act on a workspace.
+pub type Command = (Action, Workspace);
+
+/// A connection to one kind of compositor.
+#[async_trait]
+pub trait CompositorBackend: Send {
+    /// Waits for the compositor to report a change and returns the state
+    /// after it; the first call returns the current state. An error means the
+    /// connection is gone.
+    async fn next_state(&mut self) -> io::Result<State>;
+
+    /// Opens a connection for commands, used while `next_state` waits.
+    async fn commander(&mut self) -> io::Result<Box<dyn Commander>>;
+}
+
+#[async_trait]
+pub trait Commander: Send {
+    async fn run(&mut self, action: Action, workspace: &Workspace) -> io::Result<()>;
+}
+
+/// Hyprland when `$HYPRLAND_INSTANCE_SIGNATURE` is set, niri when
+/// `$NIRI_SOCKET` is, sway when `$SWAYSOCK` is, and otherwise whatever
+/// compositor `$WAYLAND_DISPLAY` leads to over `ext-workspace-v1`.
+async fn connect() -> io::Result<Box<dyn CompositorBackend>> {
+    if crate::hyprland::detected() {
+        Ok(Box::new(hyprland::Hyprland::connect().await?))
+    } else if std::env::var_os(niri_ipc::socket::SOCKET_PATH_ENV).is_some() {
+        Ok(Box::new(niri::Niri::connect().await?))
+    } else if std::env::var_os("SWAYSOCK").is_some() {
+        Ok(Box::new(sway::Sway::connect().await?))
+    } else {
+        Ok(Box::new(ext_workspace::ExtWorkspace::connect().await?))
+    }
+}
+
+/// Keeps `tx` up to date with the compositor and forwards `commands` to it,
+/// for as long as the runtime lives.
+pub async fn run(tx: watch::Sender<Option<Status>>, mut commands: UnboundedReceiver<Command>) {
+    let mut delay = MIN_RECONNECT_DELAY;
+    // Only the first of a series of failed attempts is logged
+    let mut reported = false;
+    loop {
+        let error = match connect().await {
+            Ok(mut backend) => {
+                // Clicks made while disconnected are stale by now
+                while commands.try_recv().is_ok() {}
+                let error = follow(backend.as_mut(), &tx, &mut commands, &mut delay).await;
+                reported = false;
+                error
+            }
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+                // Retrying would not make the compositor grow the protocol
+                eprintln!("vibebar-p4: {}; workspaces and window are hidden", e);
+                tx.send_replace(Some(Status::Unsupported));
+                return;
+            }
+            Err(e) => e,
+        };
+        if !reported {
+            eprintln!(
+                "vibebar-p4: compositor connection failed: {}; retrying",
+                error
+            );
+            reported = true;
+        }
+        tx.send_replace(Some(Status::Disconnected));
+
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Publishes every new state until the connection fails.
+async fn follow(
+    backend: &mut dyn CompositorBackend,
+    tx: &watch::Sender<Option<Status>>,
+    commands: &mut UnboundedReceiver<Command>,
+    delay: &mut Duration,
+) -> io::Error {
+    let mut commander = match backend.commander().await {
+        Ok(commander) => commander,
+        Err(e) => return e,
+    };
+
+    let forward = async {
+        while let Some((action, workspace)) = commands.recv().await {
+            if let Err(e) = commander.run(action, &workspace).await {
+                eprintln!(
+                    "vibebar-p4: workspace `{}`: {:?} failed: {}",
+                    workspace.name, action, e
+                );
+            }
+        }
+    };
+
+    let listen = async {
+        loop {
+            let state = match backend.next_state().await {
+                Ok(state) => Status::Connected(state),
+                Err(e) => return e,
+            };
+            *delay = MIN_RECONNECT_DELAY;
+            // Only a different `State` goes out; events it does not cover, e.g. a
+            // window resized on niri, are skipped, while any title change is sent
+            tx.send_if_modified(|current| {
+                if current.as_ref() == Some(&state) {
+                    false
+                } else {
+                    *current = Some(state);
+                    true
+                }
+            });
+        }
+    };
+
+    tokio::select! {
+        _ = forward => io::ErrorKind::BrokenPipe.into(),
+        e = listen => e,
+    }
+}
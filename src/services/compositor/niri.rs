@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use niri_ipc::Request;
+use niri_ipc::state::{EventStreamState, EventStreamStatePart};
+use std::io;
+
+use super::{Action, Commander, CompositorBackend, FocusedWindow, Layout, State, Workspace};
+use crate::niri;
+
+pub struct Niri {
+    events: niri::Socket,
+    /// Workspaces and windows arrive in separate events, and focus and
+    /// urgency changes as events of their own; this tracks them all.
+    state: EventStreamState,
+}
+
+impl Niri {
+    pub async fn connect() -> io::Result<Self> {
+        Ok(Self {
+            events: niri::Socket::event_stream().await?,
+            state: EventStreamState::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl CompositorBackend for Niri {
+    async fn next_state(&mut self) -> io::Result<State> {
+        // The stream starts with the full state, so no separate query is needed
+        let event = self.events.read_event().await?;
+        self.state.apply(event);
+
+        let workspaces = snapshot(&self.state);
+        let mut outputs: Vec<String> = workspaces.iter().filter_map(|w| w.output.clone()).collect();
+        outputs.dedup();
+        let focused_window = self
+            .state
+            .windows
+            .windows
+            .values()
+            .find(|w| w.is_focused)
+            .map(|w| FocusedWindow {
+                title: w.title.clone().unwrap_or_default(),
+                app_id: w.app_id.clone().unwrap_or_default(),
+            });
+        Ok(State {
+            workspaces,
+            outputs,
+            focused_window,
+            layout: Some(layout(&self.state)),
+        })
+    }
+
+    async fn commander(&mut self) -> io::Result<Box<dyn Commander>> {
+        Ok(Box::new(NiriCommander(niri::Socket::connect().await?)))
+    }
+}
+
+struct NiriCommander(niri::Socket);
+
+#[async_trait]
+impl Commander for NiriCommander {
+    async fn run(&mut self, action: Action, workspace: &Workspace) -> io::Result<()> {
+        let request = Request::Action(request(action, workspace.clone()));
+        self.0.send(request).await?.map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+fn request(action: Action, ws: Workspace) -> niri_ipc::Action {
+    let reference = match ws.id {
+        Some(id) => niri_ipc::WorkspaceReferenceArg::Id(id),
+        None => niri_ipc::WorkspaceReferenceArg::Name(ws.name),
+    };
+    match action {
+        Action::Focus => niri_ipc::Action::FocusWorkspace { reference },
+        Action::MoveWindow | Action::MoveWindowAndFollow => {
+            niri_ipc::Action::MoveWindowToWorkspace {
+                window_id: None,
+                reference,
+                focus: action == Action::MoveWindowAndFollow,
+            }
+        }
+    }
+}
+
+/// Workspaces in their on-screen order, output by output, each with its
+/// windows in column order.
+fn snapshot(state: &EventStreamState) -> Vec<Workspace> {
+    let mut workspaces: Vec<&niri_ipc::Workspace> = state.workspaces.workspaces.values().collect();
+    workspaces.sort_by_key(|w| (w.output.clone(), w.idx));
+
+    workspaces
+        .into_iter()
+        .map(|w| {
+            let mut windows: Vec<&niri_ipc::Window> = state
+                .windows
+                .windows
+                .values()
+                .filter(|win| win.workspace_id == Some(w.id))
+                .collect();
+            // Floating windows have no position and go last
+            windows.sort_by_key(|win| {
+                let pos = win.layout.pos_in_scrolling_layout;
+                (pos.is_none(), pos, win.id)
+            });
+            Workspace {
+                id: Some(w.id),
+                name: w.name.clone().unwrap_or_else(|| w.idx.to_string()),
+                focused: w.is_focused,
+                visible: w.is_active,
+                urgent: w.is_urgent,
+                output: w.output.clone(),
+                windows: windows
+                    .into_iter()
+                    .map(|win| win.app_id.clone().unwrap_or_default())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+fn layout(state: &EventStreamState) -> Layout {
+    let windows = &state.windows.windows;
+    let focused = state
+        .workspaces
+        .workspaces
+        .values()
+        .find(|ws| ws.is_focused);
+    let column = |w: &niri_ipc::Window| w.layout.pos_in_scrolling_layout.map(|(column, _)| column);
+
+    let (keyboard_layouts, current_layout) = match &state.keyboard_layouts.keyboard_layouts {
+        Some(layouts) => (layouts.names.clone(), usize::from(layouts.current_idx)),
+        None => (Vec::new(), 0),
+    };
+    Layout {
+        column: focused
+            .and_then(|ws| ws.active_window_id)
+            .and_then(|id| windows.get(&id))
+            .and_then(column),
+        // Columns are numbered without gaps, so the last one counts them
+        columns: focused
+            .and_then(|ws| {
+                windows
+                    .values()
+                    .filter(|w| w.workspace_id == Some(ws.id))
+                    .filter_map(column)
+                    .max()
+            })
+            .unwrap_or(0),
+        keyboard_layouts,
+        current_layout,
+    }
+}
@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::io;
+use swayipc_async::{Connection, EventStream, EventType, Node, NodeType};
+
+use super::{Action, Commander, CompositorBackend, FocusedWindow, State, Workspace};
+
+pub struct Sway {
+    events: EventStream,
+    queries: Connection,
+    /// Set once the initial state went out; later calls wait for an event.
+    started: bool,
+}
+
+impl Sway {
+    pub async fn connect() -> io::Result<Self> {
+        // Window events keep the app icons and the focused window current.
+        let events = Connection::new()
+            .await
+            .map_err(io::Error::other)?
+            .subscribe([EventType::Workspace, EventType::Window, EventType::Output])
+            .await
+            .map_err(io::Error::other)?;
+        let queries = Connection::new().await.map_err(io::Error::other)?;
+        Ok(Self {
+            events,
+            queries,
+            started: false,
+        })
+    }
+}
+
+#[async_trait]
+impl CompositorBackend for Sway {
+    async fn next_state(&mut self) -> io::Result<State> {
+        if self.started {
+            match self.events.next().await {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(io::Error::other(e)),
+                None => return Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+        self.started = true;
+        query(&mut self.queries).await.map_err(io::Error::other)
+    }
+
+    async fn commander(&mut self) -> io::Result<Box<dyn Commander>> {
+        let connection = Connection::new().await.map_err(io::Error::other)?;
+        Ok(Box::new(SwayCommander(connection)))
+    }
+}
+
+struct SwayCommander(Connection);
+
+#[async_trait]
+impl Commander for SwayCommander {
+    async fn run(&mut self, action: Action, workspace: &Workspace) -> io::Result<()> {
+        let outcomes = self
+            .0
+            .run_command(command(action, workspace))
+            .await
+            .map_err(io::Error::other)?;
+        outcomes
+            .into_iter()
+            .try_for_each(|outcome| outcome.map_err(io::Error::other))
+    }
+}
+
+fn command(action: Action, ws: &Workspace) -> String {
+    let focus = format!("workspace \"{}\"", ws.name);
+    let move_window = format!("move container to workspace \"{}\"", ws.name);
+    match action {
+        Action::Focus => focus,
+        Action::MoveWindow => move_window,
+        Action::MoveWindowAndFollow => format!("{}; {}", move_window, focus),
+    }
+}
+
+async fn query(connection: &mut Connection) -> Result<State, swayipc_async::Error> {
+    let workspaces = connection.get_workspaces().await?;
+    let outputs = connection.get_outputs().await?;
+    let tree = connection.get_tree().await?;
+    let mut windows = windows_by_workspace(&tree);
+    Ok(State {
+        workspaces: workspaces
+            .into_iter()
+            .map(|w| Workspace {
+                id: None,
+                windows: windows.remove(&w.name).unwrap_or_default(),
+                name: w.name,
+                focused: w.focused,
+                visible: w.visible,
+                urgent: w.urgent,
+                output: Some(w.output),
+            })
+            .collect(),
+        outputs: outputs
+            .into_iter()
+            .filter(|o| o.active)
+            .map(|o| o.name)
+            .collect(),
+        focused_window: focused(&tree),
+        layout: None,
+    })
+}
+
+/// App ids of the windows on each workspace, keyed by workspace name.
+fn windows_by_workspace(tree: &Node) -> HashMap<String, Vec<String>> {
+    let mut map = HashMap::new();
+    for output in &tree.nodes {
+        for workspace in &output.nodes {
+            if workspace.node_type == NodeType::Workspace
+                && let Some(name) = &workspace.name
+            {
+                let mut apps = Vec::new();
+                collect_windows(workspace, &mut apps);
+                map.insert(name.clone(), apps);
+            }
+        }
+    }
+    map
+}
+
+fn collect_windows(node: &Node, apps: &mut Vec<String>) {
+    for child in node.nodes.iter().chain(&node.floating_nodes) {
+        // Only views have a client process; containers just hold them.
+        if child.pid.is_some() {
+            apps.push(app_id(child));
+        } else {
+            collect_windows(child, apps);
+        }
+    }
+}
+
+fn focused(node: &Node) -> Option<FocusedWindow> {
+    // A focused workspace or output is not a window
+    if node.focused && node.pid.is_some() {
+        return Some(FocusedWindow {
+            title: node.name.clone().unwrap_or_default(),
+            app_id: app_id(node),
+        });
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(focused)
+}
+
+/// XWayland windows have a class instead of an app id.
+fn app_id(node: &Node) -> String {
+    node.app_id
+        .clone()
+        .or_else(|| node.window_properties.as_ref()?.class.clone())
+        .unwrap_or_default()
+}
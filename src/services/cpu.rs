@@ -0,0 +1,40 @@
+use sysinfo::{Components, System};
+
+#[derive(Clone, Debug)]
+pub struct CpuSample {
+    /// Highest current core frequency.
+    pub ghz: f64,
+    pub temperature: f32,
+    /// Usage of each core in percent.
+    pub usage: Vec<f32>,
+}
+
+pub fn sampler() -> impl FnMut() -> CpuSample {
+    let mut sys = System::new();
+    let mut components = Components::new();
+    move || {
+        sys.refresh_cpu_usage();
+        sys.refresh_cpu_specifics(sysinfo::CpuRefreshKind::nothing().with_frequency());
+        components.refresh(false);
+
+        // CPU Frequency (max)
+        let max_freq = sys.cpus().iter().map(|c| c.frequency()).max().unwrap_or(0);
+        let ghz = max_freq as f64 / 1000.0;
+
+        // Temperature (from first component for now)
+        let mut temperature = 0.0;
+        for c in components.iter() {
+            if c.label().to_lowercase().contains("cpu") || c.label().to_lowercase().contains("core")
+            {
+                temperature = c.temperature().unwrap_or(0.0);
+                break;
+            }
+        }
+
+        CpuSample {
+            ghz,
+            temperature,
+            usage: sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+        }
+    }
+}
@@ -0,0 +1,8 @@
+/// Samples the free space of the filesystem holding `path`, in GiB.
+pub fn sampler(path: String) -> impl FnMut() -> Option<f64> {
+    move || {
+        let stat = nix::sys::statvfs::statvfs(path.as_str()).ok()?;
+        let free_bytes = stat.blocks_available() * stat.fragment_size();
+        Some(free_bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+    }
+}
@@ -0,0 +1,32 @@
+#[derive(Clone, Debug)]
+pub struct GpuSample {
+    /// Busy percentage.
+    pub usage: u32,
+    pub ghz: f64,
+    pub watts: f64,
+}
+
+pub fn sample() -> GpuSample {
+    let usage = std::fs::read_to_string("/sys/class/drm/card1/device/gpu_busy_percent")
+        .ok()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or(0);
+
+    let ghz = std::fs::read_to_string("/sys/class/drm/card1/device/hwmon/hwmon7/freq1_input")
+        .ok()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .map(|f| f as f64 / 1000.0 / 1000.0 / 1000.0)
+        .unwrap_or(0.0);
+
+    let power_raw =
+        std::fs::read_to_string("/sys/class/drm/card1/device/hwmon/hwmon7/power1_average")
+            .ok()
+            .and_then(|s| s.trim().parse::<u32>().ok())
+            .unwrap_or(0);
+
+    GpuSample {
+        usage,
+        ghz,
+        watts: power_raw as f64 / 1_000_000.0,
+    }
+}
@@ -0,0 +1,16 @@
+use sysinfo::System;
+
+/// Samples used memory in percent.
+pub fn sampler() -> impl FnMut() -> f64 {
+    let mut sys = System::new();
+    move || {
+        sys.refresh_memory();
+        let used = sys.used_memory();
+        let total = sys.total_memory();
+        if total > 0 {
+            (used as f64 / total as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Data sources shared by every bar.
+//!
+//! Each source is sampled by a single task on the shared runtime no matter how
+//! many widgets show it; new values fan out to subscribers over a `watch` channel.
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::{Notify, watch};
+
+pub mod compositor;
+pub mod cpu;
+pub mod disk;
+pub mod gpu;
+pub mod memory;
+pub mod mpris;
+pub mod network;
+pub mod updates;
+pub mod volume;
+
+/// A subscription to one source.
+#[derive(Clone)]
+pub struct Feed<T> {
+    rx: watch::Receiver<Option<T>>,
+    refresh: Arc<Notify>,
+}
+
+impl<T: Clone + 'static> Feed<T> {
+    /// Asks the source for a new sample instead of waiting for its next poll.
+    pub fn refresh(&self) {
+        self.refresh.notify_one();
+    }
+
+    /// Calls `render` on the GTK main loop with the latest sample and again
+    /// whenever it changes, until the returned task is aborted.
+    pub fn attach(&self, render: impl Fn(&T) + 'static) -> glib::JoinHandle<()> {
+        let mut rx = self.rx.clone();
+        glib::MainContext::default().spawn_local(async move {
+            loop {
+                let sample = rx.borrow_and_update().clone();
+                if let Some(sample) = sample {
+                    render(&sample);
+                }
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+}
+
+struct Source<T> {
+    tx: watch::Sender<Option<T>>,
+    refresh: Arc<Notify>,
+}
+
+/// Owns the sampling tasks, keyed by source and its parameters.
+///
+/// Modules asking for the same source with the same parameters, e.g. `cpu`
+/// with the same interval on two outputs, share one sampler.
+///
+/// Samplers run until the runtime shuts down, idling while nothing subscribes.
+pub struct Services {
+    runtime: Handle,
+    sources: RefCell<HashMap<String, std::boxed::Box<dyn Any>>>,
+}
+
+impl Services {
+    pub fn new(runtime: Handle) -> Self {
+        Self {
+            runtime,
+            sources: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn cpu(&self, interval: u64) -> Feed<cpu::CpuSample> {
+        self.poll(format!("cpu:{}", interval), interval, cpu::sampler)
+    }
+
+    pub fn memory(&self, interval: u64) -> Feed<f64> {
+        self.poll(format!("memory:{}", interval), interval, memory::sampler)
+    }
+
+    pub fn gpu(&self, interval: u64) -> Feed<gpu::GpuSample> {
+        self.poll(format!("gpu:{}", interval), interval, || gpu::sample)
+    }
+
+    /// Free space in GiB of the filesystem holding `path`.
+    pub fn disk(&self, path: &str, interval: u64) -> Feed<Option<f64>> {
+        let key = format!("disk:{}:{}", interval, path);
+        let path = path.to_string();
+        self.poll(key, interval, move || disk::sampler(path))
+    }
+
+    pub fn network(&self) -> Feed<network::NetworkInfo> {
+        self.poll("network".to_string(), 1, network::sampler)
+    }
+
+    /// Pending package updates. Checks can take a while and hit the network,
+    /// so every bar shares the same one.
+    pub fn updates(
+        &self,
+        backend: updates::Backend,
+        command: Option<String>,
+        flake: &str,
+        interval: u64,
+    ) -> Feed<updates::Updates> {
+        let key = format!("updates:{:?}:{}:{:?}:{}", backend, interval, command, flake);
+        let flake = flake.to_string();
+        let runtime = self.runtime.clone();
+        self.source(key, move |tx, refresh| {
+            let interval = Duration::from_secs(interval);
+            runtime.spawn(updates::run(tx, refresh, backend, command, flake, interval));
+        })
+    }
+
+    pub fn mpris(&self) -> Feed<Option<mpris::NowPlaying>> {
+        self.poll_blocking("mpris".to_string(), 1, mpris::sampler)
+    }
+
+    /// Volume of the default sink; pushed by PulseAudio rather than polled.
+    pub fn volume(&self) -> Feed<volume::VolumeState> {
+        let runtime = self.runtime.clone();
+        self.source("volume".to_string(), move |tx, _refresh| {
+            runtime.spawn_blocking(move || volume::run(tx));
+        })
+    }
+
+    /// Workspaces and windows, pushed by the compositor, and where to send
+    /// commands for it. Every bar shares the one connection.
+    pub fn compositor(
+        &self,
+    ) -> (
+        Feed<compositor::Status>,
+        UnboundedSender<compositor::Command>,
+    ) {
+        let mut receiver = None;
+        let commands = self
+            .sources
+            .borrow_mut()
+            .entry("compositor-commands".to_string())
+            .or_insert_with(|| {
+                let (tx, rx) = mpsc::unbounded_channel::<compositor::Command>();
+                receiver = Some(rx);
+                std::boxed::Box::new(tx)
+            })
+            .downcast_ref::<UnboundedSender<compositor::Command>>()
+            .expect("service key reused with a different sample type")
+            .clone();
+
+        let runtime = self.runtime.clone();
+        let feed = self.source("compositor".to_string(), move |tx, _refresh| {
+            if let Some(commands) = receiver {
+                runtime.spawn(compositor::run(tx, commands));
+            }
+        });
+        (feed, commands)
+    }
+
+    /// A source sampled every `interval` seconds by `sampler`, built once the
+    /// source is first asked for.
+    fn poll<T, S, F>(&self, key: String, interval: u64, sampler: F) -> Feed<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        S: FnMut() -> T + Send + 'static,
+        F: FnOnce() -> S + Send + 'static,
+    {
+        let runtime = self.runtime.clone();
+        self.source(key, move |tx, refresh| {
+            runtime.spawn(async move {
+                let mut sample = sampler();
+                loop {
+                    // Idle while no widget is subscribed, e.g. between reloads.
+                    if tx.receiver_count() > 0 {
+                        // Samplers read sysfs or run short commands; let the runtime
+                        // move other tasks off this worker meanwhile.
+                        tx.send_replace(Some(tokio::task::block_in_place(&mut sample)));
+                    }
+                    wait(Duration::from_secs(interval), &refresh).await;
+                }
+            });
+        })
+    }
+
+    /// Like [`Services::poll`], for samplers holding state that is not `Send`,
+    /// such as a blocking D-Bus connection. They get a thread from the runtime's
+    /// blocking pool.
+    fn poll_blocking<T, S, F>(&self, key: String, interval: u64, sampler: F) -> Feed<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        S: FnMut() -> T,
+        F: FnOnce() -> S + Send + 'static,
+    {
+        let runtime = self.runtime.clone();
+        self.source(key, move |tx, refresh| {
+            let handle = runtime.clone();
+            runtime.spawn_blocking(move || {
+                let mut sample = sampler();
+                loop {
+                    if tx.receiver_count() > 0 {
+                        tx.send_replace(Some(sample()));
+                    }
+                    handle.block_on(wait(Duration::from_secs(interval), &refresh));
+                }
+            });
+        })
+    }
+
+    /// A source driven by `start`, which gets the sending side and the
+    /// refresh requests once the source is first asked for. Also used by
+    /// modules with samplers of their own, such as polled scripts.
+    pub fn source<T>(
+        &self,
+        key: String,
+        start: impl FnOnce(watch::Sender<Option<T>>, Arc<Notify>),
+    ) -> Feed<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let mut sources = self.sources.borrow_mut();
+        let mut created = false;
+        let source = sources.entry(key).or_insert_with(|| {
+            created = true;
+            let (tx, _) = watch::channel(None::<T>);
+            let refresh = Arc::new(Notify::new());
+            std::boxed::Box::new(Source { tx, refresh })
+        });
+        let source = source
+            .downcast_ref::<Source<T>>()
+            .expect("service key reused with a different sample type");
+
+        // Subscribed before the sampler starts, so its first sample is not
+        // skipped for want of subscribers
+        let feed = Feed {
+            rx: source.tx.subscribe(),
+            refresh: source.refresh.clone(),
+        };
+        if created {
+            start(source.tx.clone(), source.refresh.clone());
+        } else if source.tx.borrow().is_none() {
+            // Only a source that idled before its first sample needs one now;
+            // the others already hold a value
+            source.refresh.notify_one();
+        }
+        feed
+    }
+}
+
+/// Sleeps for `interval`, or until a refresh is requested.
+async fn wait(interval: Duration, refresh: &Notify) {
+    tokio::select! {
+        _ = tokio::time::sleep(interval) => {}
+        _ = refresh.notified() => {}
+    }
+}
@@ -0,0 +1,22 @@
+use mpris::{PlaybackStatus, PlayerFinder};
+
+#[derive(Clone, Debug)]
+pub struct NowPlaying {
+    pub status: PlaybackStatus,
+    pub artist: String,
+    pub title: String,
+}
+
+/// Samples the active player, if any.
+pub fn sampler() -> impl FnMut() -> Option<NowPlaying> {
+    let finder = PlayerFinder::new().unwrap();
+    move || {
+        let player = finder.find_active().ok()?;
+        let metadata = player.get_metadata().ok()?;
+        Some(NowPlaying {
+            status: player.get_playback_status().unwrap(),
+            artist: metadata.artists().map(|a| a.join(", ")).unwrap_or_default(),
+            title: metadata.title().unwrap_or_default().to_string(),
+        })
+    }
+}
@@ -0,0 +1,163 @@
+use std::fs;
+use std::process::Command;
+use std::time::Instant;
+
+#[derive(Clone, Debug)]
+pub struct NetworkInfo {
+    pub interface: String,
+    pub ssid: Option<String>,
+    pub conn_type: String,
+    pub ip_cidr: String,
+    pub strength: Option<u32>,
+    pub frequency: Option<u32>,
+    pub up_speed: u64,
+    pub down_speed: u64,
+}
+
+/// Samples the default-route interface; speeds are in bits per second since
+/// the previous sample, however long ago it was taken.
+pub fn sampler() -> impl FnMut() -> NetworkInfo {
+    // When the counters were last read, and their values then
+    let mut last: Option<(Instant, u64, u64)> = None;
+    let mut last_iface = String::new();
+
+    move || {
+        let mut info = NetworkInfo {
+            interface: "none".to_string(),
+            ssid: None,
+            conn_type: "Disconnected".to_string(),
+            ip_cidr: "0.0.0.0/0".to_string(),
+            strength: None,
+            frequency: None,
+            up_speed: 0,
+            down_speed: 0,
+        };
+
+        if let Ok(route_content) = fs::read_to_string("/proc/net/route") {
+            let mut best_iface = None;
+            let mut min_metric = u32::MAX;
+
+            for line in route_content.lines().skip(1) {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() > 6 && (parts[1] == "00000000" || parts[2] == "00000000") {
+                    let metric = parts[6].parse::<u32>().unwrap_or(u32::MAX);
+                    if metric < min_metric {
+                        min_metric = metric;
+                        best_iface = Some(parts[0].to_string());
+                    }
+                }
+            }
+
+            if let Some(iface) = best_iface {
+                // Reset bits counters if interface changed
+                if iface != last_iface {
+                    last = None;
+                    last_iface = iface.clone();
+                }
+
+                info.interface = iface.clone();
+                let is_wifi = fs::metadata(format!("/sys/class/net/{}/wireless", iface)).is_ok()
+                    || fs::metadata(format!("/sys/class/net/{}/phy80211", iface)).is_ok();
+
+                info.conn_type = if is_wifi {
+                    "WiFi".to_string()
+                } else {
+                    "Ethernet".to_string()
+                };
+
+                if is_wifi {
+                    if let Ok(output) = Command::new("iwgetid").arg("-r").output() {
+                        let s = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                        if !s.is_empty() {
+                            info.ssid = Some(s);
+                        }
+                    }
+
+                    // Signal Strength & Frequency
+                    if let Ok(wireless) = fs::read_to_string("/proc/net/wireless") {
+                        for line in wireless.lines().skip(2) {
+                            if line.contains(&iface) {
+                                let parts: Vec<&str> = line.split_whitespace().collect();
+                                if let Some(lvl) = parts.get(3) {
+                                    let level =
+                                        lvl.trim_end_matches('.').parse::<f32>().unwrap_or(0.0);
+                                    // Simple mapping: -100 to -50 -> 0 to 100
+                                    let strength = ((level + 100.0) * 2.0).clamp(0.0, 100.0) as u32;
+                                    info.strength = Some(strength);
+                                }
+                            }
+                        }
+                    }
+
+                    // Frequency using iwconfig (fallback if needed)
+                    if let Ok(output) = Command::new("iwconfig").arg(&iface).output() {
+                        let s = String::from_utf8_lossy(&output.stdout);
+                        if let Some(pos) = s.find("Frequency:") {
+                            let sub = &s[pos + 10..];
+                            let freq_str = sub.split_whitespace().next().unwrap_or("0");
+                            info.frequency =
+                                Some((freq_str.parse::<f32>().unwrap_or(0.0) * 1000.0) as u32);
+                        }
+                    }
+                }
+
+                // IP & CIDR
+                if let Ok(output) = Command::new("ip")
+                    .arg("-4")
+                    .arg("addr")
+                    .arg("show")
+                    .arg(&iface)
+                    .output()
+                {
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    for line in stdout.lines() {
+                        let parts: Vec<&str> = line.split_whitespace().collect();
+                        if let Some(pos) = parts.iter().position(|&r| r == "inet")
+                            && let Some(addr) = parts.get(pos + 1)
+                        {
+                            info.ip_cidr = addr.to_string();
+                            break;
+                        }
+                    }
+                }
+
+                // Bandwidth
+                if let Ok(dev) = fs::read_to_string("/proc/net/dev") {
+                    for line in dev.lines() {
+                        if line.contains(&iface) {
+                            let parts: Vec<&str> = line.split_whitespace().collect();
+                            // Parse safely to avoid index errors if the line format is weird
+                            let current_rx = parts
+                                .get(1)
+                                .and_then(|p| p.parse::<u64>().ok())
+                                .unwrap_or(0);
+                            let current_tx = parts
+                                .get(9)
+                                .and_then(|p| p.parse::<u64>().ok())
+                                .unwrap_or(0);
+
+                            let now = Instant::now();
+                            if let Some((at, last_rx, last_tx)) = last {
+                                let secs = now.duration_since(at).as_secs_f64();
+                                if secs > 0.0 {
+                                    let rate = |bytes: u64| (bytes as f64 * 8.0 / secs) as u64;
+                                    info.down_speed = rate(current_rx.saturating_sub(last_rx));
+                                    info.up_speed = rate(current_tx.saturating_sub(last_tx));
+                                }
+                            }
+
+                            last = Some((now, current_rx, current_tx));
+                            break;
+                        }
+                    }
+                }
+            } else {
+                // No default interface
+                last = None;
+                last_iface.clear();
+            }
+        }
+
+        info
+    }
+}
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+
+use super::wait;
+use crate::cache::Cache;
+
+/// Where pending updates come from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// `checkupdates` from pacman-contrib.
+    #[default]
+    Pacman,
+    /// Inputs of a flake whose locked revision would change on `nix flake update`.
+    Nix,
+    /// `apt list --upgradable`.
+    Apt,
+    /// `dnf check-update`.
+    Dnf,
+}
+
+impl Backend {
+    fn default_command(self) -> &'static str {
+        match self {
+            Backend::Pacman => "checkupdates",
+            Backend::Nix => "nix",
+            Backend::Apt => "apt list --upgradable",
+            Backend::Dnf => "dnf check-update",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Package {
+    pub name: String,
+    /// Installed version, when the backend reports it.
+    pub old: Option<String>,
+    pub new: String,
+}
+
+/// Pending updates, or why they could not be listed.
+pub type Updates = Result<Vec<Package>, String>;
+
+/// Checks every `interval` while any bar shows the result. The last list
+/// found is cached, so after a restart it shows at once and the next check
+/// waits until the interval has passed since the cached one.
+pub async fn run(
+    tx: watch::Sender<Option<Updates>>,
+    refresh: Arc<Notify>,
+    backend: Backend,
+    command: Option<String>,
+    flake: String,
+    interval: Duration,
+) {
+    let cache = Cache::new("updates", &format!("{:?}:{:?}:{}", backend, command, flake));
+    if let Some((packages, age)) = cache.load::<Vec<Package>>().await {
+        tx.send_replace(Some(Ok(packages)));
+        if let Some(remaining) = interval.checked_sub(age) {
+            wait(remaining, &refresh).await;
+        }
+    }
+
+    let mut sample = sampler(backend, command, flake);
+    loop {
+        if tx.receiver_count() > 0 {
+            // Checks run commands that can take a while
+            let updates = tokio::task::block_in_place(&mut sample);
+            if let Ok(packages) = &updates {
+                cache.store(packages).await;
+            }
+            tx.send_replace(Some(updates));
+        }
+        wait(interval, &refresh).await;
+    }
+}
+
+/// Checks for updates with `backend`. `command` replaces the backend's default
+/// command (e.g. `paru -Qua` for pacman) but keeps its output format; `flake`
+/// is the flake directory checked by the nix backend.
+pub fn sampler(
+    backend: Backend,
+    command: Option<String>,
+    flake: String,
+) -> impl FnMut() -> Updates {
+    move || {
+        let command = command.as_deref().unwrap_or(backend.default_command());
+        match backend {
+            Backend::Pacman => {
+                // checkupdates exits with 2 when there is nothing to update
+                let output = run_command(command, &[0, 2])?;
+                Ok(parse_pacman(&output))
+            }
+            Backend::Apt => Ok(parse_apt(&run_command(command, &[0])?)),
+            // dnf exits with 100 when updates are available
+            Backend::Dnf => Ok(parse_dnf(&run_command(command, &[0, 100])?)),
+            Backend::Nix => check_flake(command, Path::new(&flake)),
+        }
+    }
+}
+
+/// Runs `command` with `sh -c`, treating the given exit codes as success.
+fn run_command(command: &str, success: &[i32]) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| format!("failed to run `{}`: {}", command, e))?;
+    check(command, &output, success)?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn check(command: &str, output: &Output, success: &[i32]) -> Result<(), String> {
+    match output.status.code() {
+        Some(code) if success.contains(&code) => Ok(()),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.lines().last().unwrap_or_default().trim();
+            Err(format!(
+                "`{}` failed ({}): {}",
+                command, output.status, reason
+            ))
+        }
+    }
+}
+
+/// `name old -> new`, as printed by checkupdates and AUR helpers.
+fn parse_pacman(output: &str) -> Vec<Package> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let old = fields.next()?;
+            let new = fields.nth(1)?;
+            Some(Package {
+                name: name.to_string(),
+                old: Some(old.to_string()),
+                new: new.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `name/suite new arch [upgradable from: old]`, after a `Listing...` header.
+fn parse_apt(output: &str) -> Vec<Package> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, rest) = line.split_once('/')?;
+            let new = rest.split_whitespace().nth(1)?;
+            let old = line
+                .split_once("upgradable from: ")
+                .map(|(_, old)| old.trim_end_matches(']').to_string());
+            Some(Package {
+                name: name.to_string(),
+                old,
+                new: new.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `name.arch new repo` rows; everything from `Obsoleting Packages` on is skipped.
+fn parse_dnf(output: &str) -> Vec<Package> {
+    output
+        .lines()
+        .take_while(|line| !line.starts_with("Obsoleting"))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name_arch, new, _repo] = fields[..] else {
+                return None;
+            };
+            let (name, _arch) = name_arch.rsplit_once('.')?;
+            Some(Package {
+                name: name.to_string(),
+                old: None,
+                new: new.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Locks the flake's inputs into a scratch lock file and lists those whose
+/// revision would change, without touching the flake itself.
+fn check_flake(nix: &str, flake: &Path) -> Updates {
+    let current = read_lock(&flake.join("flake.lock"))?;
+
+    // Named after the flake too, as each nix source checks its own
+    let scratch = std::env::temp_dir().join(format!(
+        "vibebar-p4-{}-{:016x}.lock",
+        std::process::id(),
+        crate::cache::fnv1a(&flake.to_string_lossy())
+    ));
+    let output = Command::new(nix)
+        .args(["flake", "update", "--flake"])
+        .arg(flake)
+        .arg("--output-lock-file")
+        .arg(&scratch)
+        .output()
+        .map_err(|e| format!("failed to run `{}`: {}", nix, e))?;
+    let updated = check(nix, &output, &[0]).and_then(|()| read_lock(&scratch));
+    let _ = std::fs::remove_file(&scratch);
+    let updated = updated?;
+
+    Ok(updated
+        .into_iter()
+        .filter_map(|(name, new)| {
+            let old = current.get(&name);
+            (old != Some(&new)).then(|| Package {
+                name,
+                old: old.cloned(),
+                new,
+            })
+        })
+        .collect())
+}
+
+/// Locked revision of every input in a `flake.lock`, shortened like `git log --oneline`.
+fn read_lock(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let lock: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let nodes = lock["nodes"].as_object().cloned().unwrap_or_default();
+    Ok(nodes
+        .into_iter()
+        .filter_map(|(name, node)| {
+            let locked = &node["locked"];
+            let rev = locked["rev"].as_str().or(locked["narHash"].as_str())?;
+            Some((name, rev.chars().take(7).collect()))
+        })
+        .collect())
+}
@@ -0,0 +1,100 @@
+use crate::config;
+use gtk4::CssProvider;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use std::os::fd::{AsFd, AsRawFd};
+use std::path::{Path, PathBuf};
+
+const USER_CSS: &str = "style.css";
+
+/// The user's stylesheet, installed on top of the built-in one.
+pub struct Style {
+    user: CssProvider,
+    path: PathBuf,
+}
+
+impl Style {
+    /// Re-reads `style.css`, e.g. when asked to over the control socket.
+    pub fn reload(&self) {
+        load_user_css(&self.user, &self.path);
+    }
+}
+
+/// Installs the built-in stylesheet plus the user's `style.css` on top of it,
+/// and reloads the latter whenever it changes on disk.
+pub fn init(display: &gdk4::Display) -> Style {
+    let builtin = new_provider("<built-in style.css>".to_string());
+    builtin.load_from_data(include_str!("style.css"));
+    gtk4::style_context_add_provider_for_display(
+        display,
+        &builtin,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+
+    let dir = config::config_dir();
+    let path = dir.join(USER_CSS);
+    let user = new_provider(path.display().to_string());
+    load_user_css(&user, &path);
+    gtk4::style_context_add_provider_for_display(
+        display,
+        &user,
+        gtk4::STYLE_PROVIDER_PRIORITY_USER,
+    );
+
+    if let Err(e) = watch(&dir, user.clone(), path.clone()) {
+        eprintln!(
+            "vibebar-p4: not watching {} for style changes: {}",
+            dir.display(),
+            e
+        );
+    }
+    Style { user, path }
+}
+
+fn new_provider(origin: String) -> CssProvider {
+    let provider = CssProvider::new();
+    provider.connect_parsing_error(move |_, section, error| {
+        eprintln!("vibebar-p4: {}: {}: {}", origin, section, error.message());
+    });
+    provider
+}
+
+/// Loads the user stylesheet, or clears it when the file is gone.
+fn load_user_css(provider: &CssProvider, path: &Path) {
+    match std::fs::read_to_string(path) {
+        Ok(css) => provider.load_from_data(&css),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => provider.load_from_data(""),
+        Err(e) => eprintln!("vibebar-p4: {}: {}", path.display(), e),
+    }
+}
+
+/// Watches the config directory rather than the file itself, so editors that
+/// save by replacing the file are still picked up.
+fn watch(dir: &Path, provider: CssProvider, path: PathBuf) -> nix::Result<()> {
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+    inotify.add_watch(
+        dir,
+        AddWatchFlags::IN_CLOSE_WRITE
+            | AddWatchFlags::IN_MOVED_TO
+            | AddWatchFlags::IN_MOVED_FROM
+            | AddWatchFlags::IN_DELETE,
+    )?;
+
+    let fd = inotify.as_fd().as_raw_fd();
+    glib::unix_fd_add_local(fd, glib::IOCondition::IN, move |_, _| {
+        // Drain everything queued and reload at most once per wakeup.
+        let mut changed = false;
+        while let Ok(events) = inotify.read_events() {
+            if events.is_empty() {
+                break;
+            }
+            changed |= events
+                .iter()
+                .any(|ev| ev.name.as_deref() == Some(USER_CSS.as_ref()));
+        }
+        if changed {
+            load_user_css(&provider, &path);
+        }
+        glib::ControlFlow::Continue
+    });
+    Ok(())
+}